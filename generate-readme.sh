@@ -81,6 +81,154 @@ cargo run rm 2 3 2> /dev/null
 echo '```'
 echo
 
+echo **Undo the previous command**
+echo '```console'
+echo '$ mama undo'
+cargo run undo 2> /dev/null
+echo '```'
+echo '`mama undo N` reverts the last N commands, `mama history` lists the commands'
+echo 'that can be undone and `mama redo` reapplies undone commands.'
+echo
+
+cat << 'EOF'
+## Filters
+`ls` lists the tasks matching all terms of a filter. A term is one of
+
+| Term | Matches tasks |
+|------|---------------|
+| `roof`, `"fix the roof"` | containing the word or phrase, ignoring case |
+| `/^Call \w+/` | whose description matches the regular expression |
+| `+project`, `@context` | with exactly that project or context |
+| `pri:A`, `pri:A-C` | with a priority in the range |
+| `due<today`, `created>=2024-01-01` | whose `due`, `created`, `completed` or `t` date compares with `<`, `<=`, `=`, `>=` or `>` |
+| `done`, `undone` | that are completed or unfinished |
+| `-term` | not matching the term |
+
+`OR` separates alternatives, e.g. `mama ls +house -done OR due<today` lists
+the unfinished tasks of +house and all overdue tasks. Remember to quote terms
+with `<`, `>` or spaces from the shell.
+
+Filters used often can be saved as views in the configuration file, see
+below. `mama ls :today` then lists the tasks of the view `today`, and can be
+combined with further terms and options. A `sort:KEYS` term in a view or on
+the command line works like `--sort KEYS`. `mama views` lists the views.
+
+## Selecting tasks
+Commands that modify tasks accept task numbers (`3`), ranges (`3-7`), lists
+(`2,4,9`) and stable IDs (`id:k3x9`). Anything else is a filter as accepted by
+`ls`, e.g. `mama complete +kitchenmakeover` or
+`mama rm --done --before 2024-01-01`. If a filter matches many tasks, mama
+asks for confirmation first, unless given `-y`.
+
+## Priorities
+`mama pri 3 B` sets the priority of a task and `mama depri 3` removes it.
+`mama bump` and `mama lower` move it one letter up or down instead. A task
+without a priority is bumped to Z, the lowest, and lowering Z removes the
+priority.
+
+## Location of todo.txt
+By default, mama uses `~/todo.txt`. Another file can be chosen with, in order
+of precedence:
+
+1. the `-f`/`--file` option
+2. the `TODO_FILE` environment variable
+3. the `TODO_DIR` environment variable, naming the directory of `todo.txt`
+4. `todo_file` in the configuration file
+
+Completed tasks are archived to `done.txt` in the same directory.
+
+## Configuration
+Defaults can be changed in `~/.config/mama/config.toml`. `mama config` shows
+the effective configuration and where each value came from.
+```toml
+todo_file = "~/Sync/todo.txt"
+default_command = "ls -l"   # run when mama is called without a command
+date_format = "%d.%m.%Y"    # %Y %y %m %d %e %b %B
+auto_archive = true         # archive tasks as soon as they are completed
+stable_ids = true           # tag added tasks with an id:xxxx, see below
+backup_retention = 10       # number of saves that can be undone
+lock_timeout = 10           # seconds to wait for another mama to finish
+confirm_threshold = 5       # ask before modifying more tasks matching a filter
+
+[ls]
+flags = ["--long"]          # default options of ls
+sort = "priority,-due"      # order of ls unless --sort is given
+
+[views]                     # run with mama ls :name or mama view name
+today = "due<=today -done @work sort:pri"
+
+[colors]
+project = "bright blue"
+overdue = "bright red"
+```
+
+## Stable task IDs
+Task numbers change when tasks above them are removed or archived. A task
+added with `mama add --id`, or with `stable_ids` set, is tagged with a short
+identifier such as `id:k3x9`, which can be used in place of its number in any
+command, e.g. `mama complete id:k3x9`.
+
+## JSON output
+With `--json`, commands print a single JSON document instead of text, and
+messages meant for people go to stderr. `--format ndjson` prints one document
+per line instead, with each task in a document of its own. Every document has
+a `version`, currently 1, which changes only if fields are removed or change
+meaning.
+
+`ls` and `view` print the listed tasks:
+```json
+{"version":1,"tasks":[{"id":1,"line":"(A) 2024-01-02 Call Bob +work due:2024-01-05",
+  "completed":false,"priority":"A","completion_date":null,"creation_date":"2024-01-02",
+  "due_date":"2024-01-05","threshold_date":null,"description":"Call Bob +work due:2024-01-05",
+  "projects":["work"],"contexts":[],"tags":{"due":"2024-01-05"}}]}
+```
+Archived tasks have no `id`. Commands that change the list print the
+`changed` tasks as they are now, the `removed` tasks as they were, and all
+`tasks` in todo.txt afterwards; a modified task is both removed and changed.
+`config`, `views` and `history` print objects of the same names, and errors
+are printed as `{"version":1,"error":"..."}`.
+
+## Exporting
+`mama export --to csv|markdown|html|ical` prints the tasks that `ls` would list
+with the same options and filter, with the priority and dates in columns of
+their own. Markdown is a task list instead, with `- [x]` checkboxes for
+completed tasks and the ID and dates after each task. `-o FILE` writes to a
+file instead of the terminal, e.g.
+`mama export --to markdown --done +work -o report.md`.
+
+With `--to ical`, each task is a VTODO in an iCalendar file that calendar
+and task applications can subscribe to or import. A task keeps its UID over
+exports as long as its description and creation date stay the same; give it
+a stable `id:` tag to keep the UID when editing it too.
+
+## Importing
+`mama import FILE` adds the tasks of another application to todo.txt. The
+format is guessed from the extension of the file, or given with `--from`:
+
+| Format | Converted from |
+|--------|----------------|
+| `ical` | VTODOs, with their priority, dates, status and categories as projects and contexts |
+| `taskwarrior-json` | the output of `task export`, with projects as +projects, tags as @contexts and priorities H, M and L as A, B and C |
+| `csv` | a header row naming the columns `Task`, `Done`, `Priority`, `Created`, `Completed`, `Due`, `Project` and `Context`, as written by `mama export` |
+| `lines` | one task per line in todo.txt format, e.g. a Markdown list with `- [x]` checkboxes, whose headings are skipped |
+
+Tasks whose description is already in todo.txt are skipped, so importing the
+same file again adds nothing. `--dry-run` shows what would be added. Imported
+tasks get stable IDs like added ones, and an `id:` tag that is already taken
+is replaced with a new one.
+
+## Library
+The parsing and handling of todo.txt is also available as a Rust library,
+for tools of your own. `mama = "0.1"` in `Cargo.toml` gives `Task`,
+`TodoFile`, `Filter` and the rest, documented in the crate:
+```rust
+let mut todo = mama::TodoFile::open(std::path::Path::new("todo.txt"))?;
+let id = todo.add("(A) Call Bob +work due:2024-01-05".parse().unwrap());
+todo.complete(id);
+todo.save()?;
+```
+EOF
+
 if [ -n BACKUP ]
 then
     mv $BACKUP ~/todo.txt
@@ -1,7 +1,10 @@
-use crate::error::Error;
-use crate::Task;
-use crate::TaskPriority;
-use crate::TodoFile;
+use crate::commands::util::take_option;
+use crate::Config;
+use mama::date::parse_date;
+use mama::error::Error;
+use mama::Task;
+use mama::TaskPriority;
+use mama::TodoFile;
 
 use colored::*;
 use gregorian::Date;
@@ -11,43 +14,59 @@ pub fn description() -> &'static str {
 }
 
 pub fn usage() -> &'static str {
-    "[-p <PRIORITY>] <description of task to add>
-    -p PRIORITY   set the priority level of the added task"
+    "[-p <PRIORITY>] [-d <DATE>] [--id] <description of task to add>
+    -p PRIORITY   set the priority level of the added task
+    -d, --due DATE
+                  set the due date of the added task (YYYY-MM-DD, today,
+                  tomorrow or +N[dw])
+    --id          tag the task with a stable identifier, id:xxxx, that can
+                  be used in place of its number in other commands. This is
+                  the default if stable_ids is set in the configuration."
 }
 
-pub fn run(todo: &mut TodoFile, args: &[String]) -> Result<(), Error> {
+pub fn run(todo: &mut TodoFile, config: &Config, args: &[String]) -> Result<(), Error> {
     let mut args = Vec::from(args);
 
-    let priority = parse_priority(&mut args);
+    let priority = match take_option(&mut args, &["-p", "--priority"])? {
+        Some(priority) => Some(TaskPriority::from_letter(&priority)?),
+        None => None,
+    };
+    let due_date = match take_option(&mut args, &["--due", "-d"])? {
+        Some(date) => Some(parse_date(&date)?),
+        None => None,
+    };
+    let stable_id = match args.iter().position(|x| x == "--id") {
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+        None => config.stable_ids.value,
+    };
     let description = args.join(" ").trim().to_string();
 
     if description.is_empty() {
         return Err(Error::InsufficientArguments);
     }
 
-    println!("{} Adding '{}' to todo.txt...\n", "+".green(), description);
-    todo.add(Task {
-        description,
-        priority,
-        creation_date: Some(Date::today()),
-        ..Task::default()
-    });
-
-    match priority {
-        Some(_) => crate::commands::list::run(todo, &["-l".to_string()]),
-        None => crate::commands::list::run(todo, &[]),
+    let mut task = Task::new(&description);
+    task.priority = priority;
+    task.creation_date = Some(Date::today());
+    task.set_due_date(due_date);
+    if stable_id && task.tag("id").is_none() {
+        task.set_tag("id", &todo.unused_stable_id());
     }
-}
 
-fn parse_priority(args: &mut Vec<String>) -> Option<TaskPriority> {
-    let pri_flag = args.iter().position(|x| x == "-p" || x == "--priority");
-    match pri_flag {
-        Some(index) => {
-            let priority = format!("({})", args[index + 1].to_uppercase()).parse::<TaskPriority>();
-            args.remove(index);
-            args.remove(index);
-            priority.ok()
-        }
-        None => None,
+    say!(
+        config,
+        "{} Adding '{}' to todo.txt...\n",
+        "+".green(),
+        task.description()
+    );
+    todo.add(task);
+
+    // Show the detailed listing if the new task has details to show
+    match priority.is_some() || due_date.is_some() {
+        true => crate::commands::list::report(todo, config, &["-l".to_string()]),
+        false => crate::commands::list::report(todo, config, &[]),
     }
 }
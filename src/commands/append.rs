@@ -0,0 +1,25 @@
+use crate::commands::util::id_and_text;
+use crate::Config;
+use mama::error::Error;
+use mama::TodoFile;
+
+pub fn description() -> &'static str {
+    "Add text to the end of a task"
+}
+
+pub fn usage() -> &'static str {
+    "<id of task> <text to append>"
+}
+
+pub fn run(todo: &mut TodoFile, config: &Config, args: &[String]) -> Result<(), Error> {
+    let (id, text) = id_and_text(todo, args)?;
+
+    if let Some(task) = todo.modify(id, |task| {
+        let description = format!("{} {}", task.description(), text);
+        task.set_description(&description)
+    }) {
+        say!(config, "✏ Updated task {}, '{}'\n", id, task.description());
+    }
+
+    crate::commands::list::report(todo, config, &[])
+}
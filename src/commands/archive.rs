@@ -0,0 +1,26 @@
+use crate::Config;
+use mama::error::Error;
+use mama::TodoFile;
+
+pub fn description() -> &'static str {
+    "Move completed tasks to done.txt"
+}
+
+pub fn usage() -> &'static str {
+    "" // no arguments
+}
+
+pub fn run(todo: &mut TodoFile, config: &Config, _args: &[String]) -> Result<(), Error> {
+    archive(todo, config);
+    crate::commands::list::report(todo, config, &[])
+}
+
+/// Moves the completed tasks of `todo` to done.txt, which is written when
+/// todo.txt is saved
+pub fn archive(todo: &mut TodoFile, config: &Config) {
+    let archived = todo.archive();
+    for task in &archived {
+        say!(config, "📦 Archived '{}'", task.description());
+    }
+    say!(config, "Moved {} task(s) to done.txt\n", archived.len());
+}
@@ -0,0 +1,41 @@
+use crate::commands::util::change_priorities;
+use crate::Config;
+use mama::error::Error;
+use mama::TaskPriority;
+use mama::TodoFile;
+
+pub fn description() -> &'static str {
+    "Raise the priority of a task by one letter"
+}
+
+pub fn usage() -> &'static str {
+    "[-y] <TASKS>
+    -y, --yes     don't ask for confirmation when a filter matches many tasks
+    TASKS         IDs of tasks (3, 3-7, 2,4,9 or id:k3x9),
+                  or a filter as accepted by ls, e.g. +kitchenmakeover
+    A task without a priority is raised to Z, the lowest."
+}
+
+pub fn run(todo: &mut TodoFile, config: &Config, args: &[String]) -> Result<(), Error> {
+    change_priorities(todo, config, args, "Bump", bumped)
+}
+
+/// Returns the priority one letter higher than `priority`. A stays A.
+fn bumped(priority: Option<TaskPriority>) -> Option<TaskPriority> {
+    match priority {
+        Some(priority) => priority.higher().or(Some(priority)),
+        None => Some(TaskPriority('Z')),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn priorities_go_up_one_letter() {
+        assert_eq!(bumped(Some(TaskPriority('C'))), Some(TaskPriority('B')));
+        assert_eq!(bumped(Some(TaskPriority('A'))), Some(TaskPriority('A')));
+        assert_eq!(bumped(None), Some(TaskPriority('Z')));
+    }
+}
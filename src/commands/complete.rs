@@ -1,29 +1,50 @@
-use crate::commands::util::{args_to_task_ids, assert_ids_exist};
-use crate::error::Error;
-use crate::TodoFile;
+use crate::commands::util::targets;
+use crate::Config;
+use mama::error::Error;
+use mama::TodoFile;
 
 pub fn description() -> &'static str {
     "Mark a task as completed"
 }
 
 pub fn usage() -> &'static str {
-    "<id of finished task>..."
+    "[--archive | --no-archive] [-y] <TASKS>
+    --archive     move completed tasks to done.txt afterwards
+    --no-archive  keep completed tasks in todo.txt even if auto_archive is set
+    -y, --yes     don't ask for confirmation when a filter matches many tasks
+    TASKS         IDs of finished tasks (3, 3-7, 2,4,9 or id:k3x9),
+                  or a filter as accepted by ls, e.g. +kitchenmakeover"
 }
 
-pub fn run(todo: &mut TodoFile, args: &[String]) -> Result<(), Error> {
-    if args.is_empty() {
-        return Err(Error::InsufficientArguments);
-    }
+pub fn run(todo: &mut TodoFile, config: &Config, args: &[String]) -> Result<(), Error> {
+    let archive = match (
+        args.iter().any(|s| s == "--archive"),
+        args.iter().any(|s| s == "--no-archive"),
+    ) {
+        (_, true) => false,
+        (true, _) => true,
+        _ => config.auto_archive.value,
+    };
+    let args = args
+        .iter()
+        .filter(|&s| s != "--archive" && s != "--no-archive")
+        .cloned()
+        .collect::<Vec<String>>();
 
-    let ids = args_to_task_ids(args)?;
-    assert_ids_exist(todo, &ids)?;
+    let ids = targets(todo, config, &args, "Complete")?;
 
     for id in ids {
-        if let Some(task) = todo.complete(id) {
-            println!("✅ Completed task {}, '{}'", id, task.description);
+        if let Some((task, next_id)) = todo.complete(id) {
+            say!(config, "✅ Completed task {}, '{}'", id, task.description());
+            if let Some(next_id) = next_id {
+                say!(config, "🔁 Task {} recurs as task {}", id, next_id);
+            }
         }
     }
-    println!();
+    say!(config);
 
-    crate::commands::list::run(todo, &[])
+    if archive {
+        crate::commands::archive::archive(todo, config);
+    }
+    crate::commands::list::report(todo, config, &[])
 }
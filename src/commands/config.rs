@@ -0,0 +1,67 @@
+use crate::config::OutputFormat;
+use crate::json;
+use crate::Config;
+use mama::error::Error;
+use mama::TodoFile;
+use serde_json::{json, Map, Value};
+
+pub fn description() -> &'static str {
+    "Show the effective configuration"
+}
+
+pub fn usage() -> &'static str {
+    "" // no arguments
+}
+
+pub fn run(_todo: &mut TodoFile, config: &Config, _args: &[String]) -> Result<(), Error> {
+    if config.output.value != OutputFormat::Human {
+        let mut settings = Map::new();
+        for (key, value, source) in config.entries() {
+            let value = json!({"value": toml_to_json(&value), "source": source.to_string()});
+            settings.insert(key, value);
+        }
+        json::print("config", Value::Object(settings));
+        return Ok(());
+    }
+
+    match Config::path() {
+        Some(path) if path.exists() => println!("# Configuration file: {}\n", path.display()),
+        Some(path) => println!("# Configuration file: {} (not found)\n", path.display()),
+        None => println!("# Unable to determine configuration directory\n"),
+    }
+
+    let entries = config.entries();
+    let key_width = entries.iter().map(|(k, _, _)| k.len()).max().unwrap_or(0);
+    let value_width = entries.iter().map(|(_, v, _)| v.len()).max().unwrap_or(0);
+    for (key, value, source) in entries {
+        println!(
+            "{:kw$} = {:vw$}  # {}",
+            key,
+            value,
+            source,
+            kw = key_width,
+            vw = value_width
+        );
+    }
+
+    Ok(())
+}
+
+/// Converts a value formatted as in the configuration file to JSON
+fn toml_to_json(value: &str) -> Value {
+    fn convert(value: toml::Value) -> Value {
+        match value {
+            toml::Value::String(s) => Value::from(s),
+            toml::Value::Integer(n) => Value::from(n),
+            toml::Value::Float(x) => Value::from(x),
+            toml::Value::Boolean(b) => Value::from(b),
+            toml::Value::Array(values) => values.into_iter().map(convert).collect(),
+            _ => Value::Null,
+        }
+    }
+
+    match format!("value = {}", value).parse::<toml::Table>() {
+        Ok(mut table) => table.remove("value").map_or(Value::Null, convert),
+        Err(_) => Value::from(value),
+    }
+}
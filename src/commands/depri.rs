@@ -0,0 +1,19 @@
+use crate::commands::util::change_priorities;
+use crate::Config;
+use mama::error::Error;
+use mama::TodoFile;
+
+pub fn description() -> &'static str {
+    "Remove the priority of a task"
+}
+
+pub fn usage() -> &'static str {
+    "[-y] <TASKS>
+    -y, --yes     don't ask for confirmation when a filter matches many tasks
+    TASKS         IDs of tasks (3, 3-7, 2,4,9 or id:k3x9),
+                  or a filter as accepted by ls, e.g. +kitchenmakeover"
+}
+
+pub fn run(todo: &mut TodoFile, config: &Config, args: &[String]) -> Result<(), Error> {
+    change_priorities(todo, config, args, "Deprioritize", |_| None)
+}
@@ -0,0 +1,208 @@
+use crate::commands::util::{args_to_task_ids, assert_ids_exist, confirm};
+use crate::Config;
+use mama::error::Error;
+use mama::Task;
+use mama::TodoFile;
+
+pub fn description() -> &'static str {
+    "Edit a task, or the whole list, in a text editor"
+}
+
+pub fn usage() -> &'static str {
+    "[<id of task>]
+    Without an ID, all of todo.txt is opened for editing. The editor is
+    $VISUAL or $EDITOR, or vi if neither is set."
+}
+
+pub fn run(todo: &mut TodoFile, config: &Config, args: &[String]) -> Result<(), Error> {
+    match args {
+        [] => edit_file(todo, config)?,
+        [id] => edit_task(todo, config, id)?,
+        _ => return Err(Error::InvalidArgument("expected a single task".to_string())),
+    }
+
+    crate::commands::list::report(todo, config, &[])
+}
+
+fn edit_task(todo: &mut TodoFile, config: &Config, arg: &str) -> Result<(), Error> {
+    let id = match args_to_task_ids(todo, &[arg.to_string()])?.as_slice() {
+        [id] => *id,
+        _ => return Err(Error::InvalidArgument("expected a single task".to_string())),
+    };
+    assert_ids_exist(todo, &[id])?;
+
+    let original = todo.line(id).unwrap();
+    let mut text = original.clone();
+    let task = loop {
+        text = edit_in_editor(&text)?;
+        let lines = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect::<Vec<&str>>();
+        let problems = match lines.as_slice() {
+            [] => return Err(Error::Cancelled),
+            [line] => problems(line),
+            _ => vec!["a task must be a single line".to_string()],
+        };
+        if problems.is_empty() {
+            break lines[0].parse::<Task>().unwrap();
+        }
+        reopen_or_cancel(&problems)?;
+    };
+
+    // Leave the line untouched unless the task itself was edited
+    let unchanged = text.trim_end_matches(['\n', '\r']) == original;
+    if unchanged || todo.iter().any(|(x, t)| *x == id && *t == task) {
+        say!(config, "No changes made to task {}\n", id);
+    } else if let Some(task) = todo.modify(id, |x| *x = task) {
+        say!(config, "✏ Updated task {}, '{}'\n", id, task.description());
+    }
+    Ok(())
+}
+
+fn edit_file(todo: &mut TodoFile, config: &Config) -> Result<(), Error> {
+    let original = todo.contents();
+    let mut text = original.clone();
+    loop {
+        text = edit_in_editor(&text)?;
+        let problems = problems(&text);
+        if problems.is_empty() {
+            break;
+        }
+        reopen_or_cancel(&problems)?;
+    }
+
+    if text == original {
+        say!(config, "No changes made to {}\n", todo.path().display());
+    } else {
+        todo.replace_contents(&text);
+        say!(config, "✏ Updated {}\n", todo.path().display());
+    }
+    Ok(())
+}
+
+/// Returns a description of each invalid due date, threshold date and
+/// recurrence in `contents`
+fn problems(contents: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (n, line) in contents.lines().enumerate() {
+        let task = match line.parse::<Task>() {
+            Ok(task) => task,
+            Err(_) => continue, // blank lines are fine
+        };
+        let mut report = |key: &str, what: &str| {
+            problems.push(format!(
+                "line {}: '{}:{}' is not a valid {}",
+                n + 1,
+                key,
+                task.tag(key).unwrap_or_default(),
+                what
+            ))
+        };
+        if task.tag("due").is_some() && task.due_date().is_none() {
+            report("due", "date");
+        }
+        if task.tag("t").is_some() && task.threshold_date().is_none() {
+            report("t", "date");
+        }
+        if task.tag("rec").is_some() && task.recurrence().is_none() {
+            report("rec", "recurrence");
+        }
+    }
+    problems
+}
+
+/// Shows `problems` and asks whether to return to the editor to fix them
+fn reopen_or_cancel(problems: &[String]) -> Result<(), Error> {
+    for problem in problems {
+        eprintln!("⚠ {}", problem);
+    }
+    match confirm("Edit again?", true)? {
+        true => Ok(()),
+        false => Err(Error::Cancelled),
+    }
+}
+
+/// Opens `text` in the user's editor and returns the edited text
+fn edit_in_editor(text: &str) -> Result<String, Error> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let io_error = |e: std::io::Error| Error::Io(format!("Unable to edit: {}", e));
+    let path = write_temporary(text).map_err(io_error)?;
+
+    // The editor may have arguments of its own, e.g. "code --wait"
+    let mut words = editor.split_whitespace();
+    let status = std::process::Command::new(words.next().unwrap_or("vi"))
+        .args(words)
+        .arg(&path)
+        .status();
+    let edited = std::fs::read_to_string(&path);
+    let _ = std::fs::remove_file(&path);
+
+    match status.map_err(io_error)? {
+        status if status.success() => edited.map_err(io_error),
+        status => Err(Error::Io(format!("{} exited with {}", editor, status))),
+    }
+}
+
+/// Writes `text` to a new file in the temporary directory that only the
+/// user can read. The file is never one that already existed, so another
+/// user cannot plant a symlink at its path.
+fn write_temporary(text: &str) -> std::io::Result<std::path::PathBuf> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut attempt = 0;
+    loop {
+        let path =
+            std::env::temp_dir().join(format!("mama-edit-{}-{}.txt", std::process::id(), attempt));
+        match options.open(&path) {
+            Ok(mut file) => {
+                file.write_all(text.as_bytes())?;
+                return Ok(path);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists && attempt < 100 => {
+                attempt += 1
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temporary_files_are_new_and_private() {
+        let first = write_temporary("Call mom").unwrap();
+        let second = write_temporary("Call dad").unwrap();
+        assert_ne!(first, second);
+        assert_eq!(std::fs::read_to_string(&first).unwrap(), "Call mom");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&first).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        std::fs::remove_file(first).unwrap();
+        std::fs::remove_file(second).unwrap();
+    }
+
+    #[test]
+    fn invalid_dates_are_reported() {
+        assert!(problems("Call mom due:2021-06-09 t:2021-06-01 rec:1w\n\n").is_empty());
+        assert_eq!(
+            problems("ok\nCall mom due:2021-13-01\nWater plants rec:often"),
+            [
+                "line 2: 'due:2021-13-01' is not a valid date",
+                "line 3: 'rec:often' is not a valid recurrence"
+            ]
+        );
+    }
+}
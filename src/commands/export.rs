@@ -0,0 +1,39 @@
+use crate::commands::util::take_option;
+use crate::Config;
+use mama::error::Error;
+use mama::export::{render, ExportFormat};
+use mama::TodoFile;
+
+pub fn description() -> &'static str {
+    "Export tasks to CSV, Markdown, HTML or iCalendar"
+}
+
+pub fn usage() -> &'static str {
+    "--to <FORMAT> [-o <FILE>] [<LS ARGUMENTS>]
+    --to FORMAT   csv, markdown, html or ical
+    -o, --output FILE
+                  write to FILE instead of the standard output
+    LS ARGUMENTS  options and filter selecting the tasks as in ls, e.g.
+                  --done +work"
+}
+
+pub fn run(todo: &mut TodoFile, config: &Config, args: &[String]) -> Result<(), Error> {
+    let mut args = Vec::from(args);
+    let format = take_option(&mut args, &["--to"])?
+        .ok_or(Error::InsufficientArguments)?
+        .parse::<ExportFormat>()?;
+    let output = take_option(&mut args, &["-o", "--output"])?;
+
+    let tasks = crate::commands::list::listed_tasks(todo, config, &args)?;
+    let document = render(format, &tasks, &config.date_format.value);
+    match output {
+        Some(path) => {
+            std::fs::write(&path, document)
+                .map_err(|e| Error::Io(format!("Unable to write {}: {}", path, e)))?;
+            say!(config, "📄 Exported {} task(s) to {}", tasks.len(), path);
+        }
+        None => print!("{}", document),
+    }
+
+    Ok(())
+}
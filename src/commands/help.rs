@@ -1,6 +1,7 @@
 use crate::commands::Commands;
-use crate::error::Error;
-use crate::TodoFile;
+use crate::Config;
+use mama::error::Error;
+use mama::TodoFile;
 use strum::{EnumMessage, IntoEnumIterator};
 
 use std::fmt::Write;
@@ -13,22 +14,34 @@ pub fn description() -> &'static str {
 pub fn usage() -> String {
     let mut s = String::new();
     writeln!(s, "A command line application for managing todo.txt\n").ok();
-    writeln!(s, "Usage: mama <command> [arguments]\n").ok();
+    writeln!(
+        s,
+        "Usage: mama [-f <file>] [--json] <command> [arguments]\n"
+    )
+    .ok();
+    writeln!(s, "Options:").ok();
+    writeln!(s, "-f, --file    use <file> instead of ~/todo.txt").ok();
+    writeln!(s, "--json        print the results as JSON").ok();
+    writeln!(
+        s,
+        "--format FMT  print the results as text, json or ndjson\n"
+    )
+    .ok();
 
     writeln!(s, "Available commands:").ok();
     for cmd in Commands::iter() {
-        let name = cmd.get_serializations().get(0).unwrap_or(&"");
+        let name = cmd.get_serializations().first().unwrap_or(&"");
         writeln!(s, "{:<14}{}", name, cmd.description()).ok();
     }
     s
 }
 
-pub fn run(_f: &mut TodoFile, args: &[String]) -> Result<(), Error> {
-    let arg = args.get(0).map(|s| Commands::from_str(s).ok()).flatten();
+pub fn run(_f: &mut TodoFile, _config: &Config, args: &[String]) -> Result<(), Error> {
+    let arg = args.first().and_then(|s| Commands::from_str(s).ok());
 
     match arg {
         Some(x) => {
-            let cmd = x.get_serializations().get(0).unwrap();
+            let cmd = x.get_serializations().first().unwrap();
             println!("mama {} - {}\n", cmd, x.description());
             println!("Usage: mama {} {}", cmd, x.usage())
         }
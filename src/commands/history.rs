@@ -0,0 +1,50 @@
+use crate::config::OutputFormat;
+use crate::json;
+use crate::Config;
+use colored::*;
+use mama::error::Error;
+use mama::History;
+use mama::TodoFile;
+
+pub fn description() -> &'static str {
+    "Show the commands that can be undone"
+}
+
+pub fn usage() -> &'static str {
+    "" // no arguments
+}
+
+pub fn run(todo: &mut TodoFile, config: &Config, _args: &[String]) -> Result<(), Error> {
+    let history = History::open(&todo.history_dir())
+        .map_err(|e| Error::Io(format!("Unable to read the undo history: {}", e)))?;
+
+    if config.output.value != OutputFormat::Human {
+        let cursor = history.cursor();
+        let entries = history.entries().iter().enumerate().rev();
+        let entries = entries.map(|(position, entry)| {
+            serde_json::json!({
+                "undo": (position < cursor).then(|| cursor - position),
+                "time": entry.time(),
+                "timestamp": entry.timestamp,
+                "command": entry.command,
+            })
+        });
+        json::print("history", entries.collect());
+        return Ok(());
+    }
+
+    // The "Undo" column is the N of the `undo N` that reverts the entry
+    println!("{:>4} {:16} Command", "Undo", "Time (UTC)");
+    let cursor = history.cursor();
+    for (position, entry) in history.entries().iter().enumerate().rev() {
+        if position < cursor {
+            let steps = cursor - position;
+            println!("{:>4} {} {}", steps, entry.time(), entry.command);
+        } else {
+            let line = format!("{:>4} {} {} (undone)", "", entry.time(), entry.command);
+            println!("{}", line.dimmed());
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use crate::commands::util::take_option;
+use crate::config::OutputFormat;
+use crate::Config;
+use colored::*;
+use mama::error::Error;
+use mama::import::{parse, remove_duplicates, ImportFormat};
+use mama::Task;
+use mama::TodoFile;
+use std::path::Path;
+
+pub fn description() -> &'static str {
+    "Add tasks from iCalendar, Taskwarrior, CSV or a plain list"
+}
+
+pub fn usage() -> &'static str {
+    "[--from <FORMAT>] [--dry-run] <FILE>
+    --from FORMAT the format of FILE: ical, taskwarrior-json, csv or lines.
+                  By default it is guessed from the extension of FILE.
+    --dry-run     show the tasks that would be added without adding them
+
+    Tasks that are already in todo.txt, with the same description, are
+    skipped. An id: tag that another task already has is replaced with a
+    new one, and with stable_ids set, every task gets one."
+}
+
+pub fn run(todo: &mut TodoFile, config: &Config, args: &[String]) -> Result<(), Error> {
+    let mut args = Vec::from(args);
+    let format = match take_option(&mut args, &["--from"])? {
+        Some(format) => Some(format.parse::<ImportFormat>()?),
+        None => None,
+    };
+    let dry_run = match args.iter().position(|x| x == "--dry-run") {
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+        None => false,
+    };
+    let path = match args.as_slice() {
+        [path] => path,
+        [] => return Err(Error::InsufficientArguments),
+        _ => return Err(Error::InvalidArgument(args[1..].join(" "))),
+    };
+    let format = format
+        .or_else(|| ImportFormat::of(Path::new(path)))
+        .ok_or_else(|| {
+            Error::InvalidArgument(format!("unable to tell the format of {}, use --from", path))
+        })?;
+
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| Error::Io(format!("Unable to read {}: {}", path, e)))?;
+    let (tasks, duplicates) = remove_duplicates(todo.tasks(), parse(format, &contents)?);
+
+    if dry_run {
+        if config.output.value != OutputFormat::Human {
+            let tasks = tasks.iter().map(|task| crate::json::task(None, task));
+            crate::json::print_tasks(config.output.value, "tasks", tasks.collect());
+            return Ok(());
+        }
+        for task in &tasks {
+            println!("{} {}", "+".green(), task);
+        }
+        for task in &duplicates {
+            println!("{} {} (already in todo.txt)", "=".dimmed(), task);
+        }
+        say!(
+            config,
+            "\nWould import {} task(s) from {}, skipping {} duplicate(s)",
+            tasks.len(),
+            path,
+            duplicates.len()
+        );
+        return Ok(());
+    }
+
+    say!(
+        config,
+        "📥 Imported {} task(s) from {}, skipping {} duplicate(s)\n",
+        tasks.len(),
+        path,
+        duplicates.len()
+    );
+    for mut task in tasks {
+        add_stable_id(todo, &mut task, config.stable_ids.value);
+        todo.add(task);
+    }
+
+    crate::commands::list::report(todo, config, &[])
+}
+
+/// Gives `task` a new stable ID if its id: tag is already taken, or if it
+/// has none and `stable_ids` is set, as for added tasks
+fn add_stable_id(todo: &TodoFile, task: &mut Task, stable_ids: bool) {
+    let needed = match task.tag("id") {
+        Some(id) => todo.find_stable_id(id).is_some(),
+        None => stable_ids,
+    };
+    if needed {
+        task.set_tag("id", &todo.unused_stable_id());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stable_ids_are_unique() {
+        let mut todo = TodoFile::default();
+        todo.add("Call Bob id:k3x9".parse().unwrap());
+
+        let mut task = "Pay rent id:k3x9".parse::<Task>().unwrap();
+        add_stable_id(&todo, &mut task, false);
+        let id = task.tag("id").unwrap();
+        assert_ne!(id, "k3x9");
+        assert_eq!(id.len(), 4);
+
+        let mut task = "Water plants id:p2q7".parse::<Task>().unwrap();
+        add_stable_id(&todo, &mut task, true);
+        assert_eq!(task.tag("id"), Some("p2q7"));
+
+        let mut task = "Water plants".parse::<Task>().unwrap();
+        add_stable_id(&todo, &mut task, false);
+        assert_eq!(task.tag("id"), None);
+        add_stable_id(&todo, &mut task, true);
+        assert!(task.tag("id").is_some());
+    }
+}
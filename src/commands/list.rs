@@ -1,11 +1,21 @@
-use crate::error::Error;
-use crate::Task;
-use crate::TaskId;
-use crate::TodoFile;
+use crate::commands::util::take_option;
+use crate::config::OutputFormat;
+use crate::json;
+use crate::Config;
+use mama::date::{format_date, parse_date, parse_period};
+use mama::error::Error;
+use mama::sort::UnknownSortField;
+use mama::DoneFile;
+use mama::Filter;
+use mama::SortOrder;
+use mama::Task;
+use mama::TaskId;
+use mama::TodoFile;
 
 use colored::*;
 use gregorian::Date;
 use iterate::iterate;
+use serde_json::Value;
 use std::cmp::max;
 use std::io::{self, Write};
 use terminal_size::{terminal_size, Width};
@@ -15,55 +25,470 @@ pub fn description() -> &'static str {
 }
 
 pub fn usage() -> &'static str {
-    "[-l/--long] [<FILTER>]
+    "[-l/--long] [-a/--all] [--future] [--done] [--overdue] [--due-before <DATE>]
+        [--due-within <PERIOD>] [--before <DATE>] [--sort <KEYS>] [-r/--reverse]
+        [--group-by <FIELD>] [<FILTER>]
     -l, --long    detailed output
-    FILTER        only show tasks matching words in FILTER"
+    -a, --all     show all tasks, including future and archived ones
+    --future      also show tasks whose threshold date (t:) is in the future
+    --done        only show completed tasks, including those archived in
+                  done.txt
+    --overdue     only show unfinished tasks whose due date has passed
+    --due-before DATE
+                  only show tasks due before DATE
+    --due-within PERIOD
+                  only show tasks due within PERIOD from today, e.g. 7d or 2w
+    --before DATE only show tasks completed before DATE, or if unfinished,
+                  created before DATE
+    --sort KEYS   order tasks by comma-separated KEYS, any of priority, due,
+                  created, completed, project, context and description.
+                  A key prefixed with '-' sorts in reverse, e.g.
+                  --sort priority,-due. Tasks missing a key go last.
+    -r, --reverse reverse the direction of every sort key, or without
+                  any, list tasks in reverse file order
+    --group-by FIELD
+                  list tasks under a heading for each project, context,
+                  priority, due date or value of a tag, with FIELD one of
+                  project, context, priority, due and tag:KEY
+    FILTER        only show tasks matching all terms in FILTER:
+                  word, \"a phrase\", /regex/  text in the description
+                  +project, @context     exact project or context
+                  pri:A, pri:A-C         priority in range
+                  due<today, created>=2024-01-01, completed=DATE, t>DATE
+                  done, undone           completed or unfinished tasks
+                  -TERM                  tasks not matching TERM
+                  TERMS OR TERMS         tasks matching either side"
 }
 
-pub fn run(todo: &mut TodoFile, args: &[String]) -> Result<(), Error> {
-    let detailed_output = args.iter().any(|s| s == "-l" || s == "--long");
-    let filters = args
-        .iter()
-        .filter(|&s| s != "-l" && s != "--long")
-        .cloned()
-        .collect::<Vec<String>>();
+/// Command line options of `ls`
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    detailed_output: bool,
+    /// List tasks whose threshold date has not been reached
+    show_future: bool,
+    /// List tasks archived in done.txt
+    show_archived: bool,
+    /// List only completed tasks
+    done: bool,
+    overdue: bool,
+    /// Only tasks due before this date are listed
+    due_before: Option<Date>,
+    /// Only tasks due on or before this date are listed
+    due_by: Option<Date>,
+    /// Only tasks completed, or if unfinished, created before this date
+    /// are listed
+    before: Option<Date>,
+    /// Overrides the sort order in the configuration
+    sort: Option<SortOrder>,
+    reverse: bool,
+    group_by: Option<Grouping>,
+    filter: Filter,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, Error> {
+        let mut args = Vec::from(args);
+
+        let due_before = match take_option(&mut args, &["--due-before"])? {
+            Some(date) => Some(parse_date(&date)?),
+            None => None,
+        };
+        let due_by = match take_option(&mut args, &["--due-within"])? {
+            Some(period) => Some(Date::today().add_days(parse_period(&period)?)),
+            None => None,
+        };
+        let before = match take_option(&mut args, &["--before"])? {
+            Some(date) => Some(parse_date(&date)?),
+            None => None,
+        };
+        let sort = match take_option(&mut args, &["--sort"])? {
+            Some(keys) => Some(parse_sort(&keys)?),
+            None => None,
+        };
+
+        let group_by = match take_option(&mut args, &["--group-by"])? {
+            Some(field) => Some(field.parse::<Grouping>()?),
+            None => None,
+        };
+
+        let mut filter = Vec::new();
+        let mut options = Options {
+            due_before,
+            due_by,
+            before,
+            sort,
+            group_by,
+            ..Options::default()
+        };
+        for arg in args {
+            match arg.as_str() {
+                "-l" | "--long" => options.detailed_output = true,
+                "-a" | "--all" => {
+                    options.show_future = true;
+                    options.show_archived = true;
+                }
+                "--future" => options.show_future = true,
+                "--done" => {
+                    options.done = true;
+                    options.show_archived = true;
+                }
+                "--overdue" => options.overdue = true,
+                "-r" | "--reverse" => options.reverse = true,
+                _ if arg.starts_with("sort:") => {
+                    options.sort = Some(parse_sort(&arg["sort:".len()..])?)
+                }
+                _ => filter.push(arg),
+            }
+        }
+        options.filter = Filter::parse(&filter)?;
+        Ok(options)
+    }
+
+    /// Combines the options with `defaults`, e.g. the flags in the
+    /// configuration. Options with values take precedence over the
+    /// defaults, and the filters must both match.
+    fn or(self, defaults: Options) -> Self {
+        Options {
+            detailed_output: self.detailed_output || defaults.detailed_output,
+            show_future: self.show_future || defaults.show_future,
+            show_archived: self.show_archived || defaults.show_archived,
+            done: self.done || defaults.done,
+            overdue: self.overdue || defaults.overdue,
+            due_before: self.due_before.or(defaults.due_before),
+            due_by: self.due_by.or(defaults.due_by),
+            before: self.before.or(defaults.before),
+            sort: self.sort.or(defaults.sort),
+            reverse: self.reverse || defaults.reverse,
+            group_by: self.group_by.or(defaults.group_by),
+            filter: defaults.filter.and(self.filter),
+        }
+    }
+
+    /// Checks whether `task` should be listed
+    fn includes(&self, task: &Task) -> bool {
+        let due = task.due_date();
+        (self.show_future || !task.is_future())
+            && (!self.done || task.completed)
+            && (!self.overdue || task.is_overdue())
+            && self
+                .due_before
+                .is_none_or(|date| due.is_some_and(|due| due < date))
+            && self
+                .due_by
+                .is_none_or(|date| due.is_some_and(|due| due <= date))
+            && self.before.is_none_or(|date| {
+                let day = match task.completed {
+                    true => task.completion_date,
+                    false => task.creation_date,
+                };
+                day.is_some_and(|day| day < date)
+            })
+            && self.filter.matches(task)
+    }
+}
+
+/// A task to list, and its ID unless it has been archived
+type Listed<'a> = (Option<&'a TaskId>, &'a Task);
+
+/// What `ls --group-by` groups tasks by
+#[derive(Debug, PartialEq)]
+enum Grouping {
+    Project,
+    Context,
+    Priority,
+    Due,
+    Tag(String),
+}
+
+impl std::str::FromStr for Grouping {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "project" => Ok(Grouping::Project),
+            "context" => Ok(Grouping::Context),
+            "pri" | "priority" => Ok(Grouping::Priority),
+            "due" => Ok(Grouping::Due),
+            _ => match s.strip_prefix("tag:").filter(|key| !key.is_empty()) {
+                Some(key) => Ok(Grouping::Tag(key.to_string())),
+                None => Err(Error::InvalidArgument(format!(
+                    "unable to group by '{}'",
+                    s
+                ))),
+            },
+        }
+    }
+}
+
+impl Grouping {
+    /// Returns the headings of the groups `task` belongs to, which are
+    /// also their sort keys. A task can belong to several groups, or none,
+    /// but only once to each group.
+    fn headings(&self, config: &Config, task: &Task) -> Vec<String> {
+        let headings: Vec<String> = match self {
+            Grouping::Project => task.projects().iter().map(|p| format!("+{}", p)).collect(),
+            Grouping::Context => task.contexts().iter().map(|c| format!("@{}", c)).collect(),
+            Grouping::Priority => task.priority.iter().map(|p| format!("({})", p)).collect(),
+            Grouping::Due => task
+                .due_date()
+                .iter()
+                .map(|date| format_date(*date, &config.date_format.value))
+                .collect(),
+            Grouping::Tag(key) => task
+                .tags()
+                .iter()
+                .filter(|(k, _)| k == key)
+                .map(|(k, v)| format!("{}:{}", k, v))
+                .collect(),
+        };
+        let mut unique = Vec::new();
+        for heading in headings {
+            if !unique.contains(&heading) {
+                unique.push(heading);
+            }
+        }
+        unique
+    }
+
+    /// Sorts `tasks` into groups, keeping their order within each group.
+    /// Tasks that belong to no group are collected under "(none)", last.
+    fn group<'a>(&self, config: &Config, tasks: &[Listed<'a>]) -> Vec<(String, Vec<Listed<'a>>)> {
+        // Due dates are grouped by date in chronological order, since a
+        // date format may show different dates the same, e.g. "%B"
+        let mut groups: Vec<(Option<Date>, String, Vec<_>)> = Vec::new();
+        let mut ungrouped = Vec::new();
+        for &(id, task) in tasks {
+            let headings = self.headings(config, task);
+            if headings.is_empty() {
+                ungrouped.push((id, task));
+            }
+            let date = match self {
+                Grouping::Due => task.due_date(),
+                _ => None,
+            };
+            for heading in headings {
+                match groups
+                    .iter_mut()
+                    .find(|(d, h, _)| *d == date && *h == heading)
+                {
+                    Some((_, _, group)) => group.push((id, task)),
+                    None => groups.push((date, heading, vec![(id, task)])),
+                }
+            }
+        }
+
+        match self {
+            Grouping::Due => groups.sort_by_key(|(date, _, _)| *date),
+            _ => groups.sort_by_key(|(_, heading, _)| heading.to_lowercase()),
+        }
+        let mut groups = groups
+            .into_iter()
+            .map(|(_, heading, group)| (heading, group))
+            .collect::<Vec<_>>();
+        if !ungrouped.is_empty() {
+            groups.push(("(none)".to_string(), ungrouped));
+        }
+        groups
+    }
+}
+
+fn parse_sort(keys: &str) -> Result<SortOrder, Error> {
+    keys.parse::<SortOrder>().map_err(|UnknownSortField(key)| {
+        Error::InvalidArgument(format!("unknown sort key '{}'", key))
+    })
+}
+
+/// Replaces every `:name` in `args` with the arguments of the view `name`
+/// in the configuration
+fn expand_views(config: &Config, args: &[String]) -> Result<Vec<String>, Error> {
+    let mut expanded = Vec::new();
+    for arg in args {
+        match arg.strip_prefix(':').filter(|name| !name.is_empty()) {
+            Some(name) => match config.views.get(name) {
+                Some(view) => expanded.extend(split_view(&view.value)),
+                None => return Err(Error::InvalidArgument(format!("unknown view '{}'", name))),
+            },
+            None => expanded.push(arg.clone()),
+        }
+    }
+    Ok(expanded)
+}
+
+/// Splits a view into arguments at whitespace, except within double
+/// quotes, like a shell would
+fn split_view(view: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg: Option<String> = None;
+    let mut quoted = false;
+    for c in view.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                arg.get_or_insert_with(String::new);
+            }
+            c if c.is_whitespace() && !quoted => args.extend(arg.take()),
+            c => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(arg);
+    args
+}
+
+pub fn run(todo: &mut TodoFile, config: &Config, args: &[String]) -> Result<(), Error> {
+    // The options given on the command line override the configured flags
+    let defaults = Options::parse(&expand_views(config, &config.ls_flags.value)?)?;
+    let options = Options::parse(&expand_views(config, args)?)?.or(defaults);
 
     // ID column width is 2 for the header "ID" or the length of
     // the longest ID, whichever is largest
-    let id_column_width = max(2, todo.tasks().len().to_string().len());
+    let id_column_width = max(2, todo.tasks().count().to_string().len());
 
-    print_header(detailed_output, id_column_width);
-    for (id, task) in todo.iter() {
-        if filters.is_empty()
-            || filters
-                .iter()
-                .any(|s| task.description.to_lowercase().contains(&s.to_lowercase()))
-        {
-            print_task(id, task, detailed_output, id_column_width);
+    let done = open_done_file(todo, &options)?;
+    let tasks = select(todo, &done, config, &options);
+
+    if config.output.value != OutputFormat::Human {
+        let tasks = tasks.iter().map(|(id, task)| json::task(*id, task));
+        json::print_tasks(config.output.value, "tasks", tasks.collect());
+        return Ok(());
+    }
+
+    print_header(config, options.detailed_output, id_column_width);
+    match &options.group_by {
+        Some(grouping) => {
+            for (heading, group) in grouping.group(config, &tasks) {
+                println!("\n{} ({})", heading.bold(), group.len());
+                for (id, task) in group {
+                    print_task(config, id, task, options.detailed_output, id_column_width);
+                }
+            }
+        }
+        None => {
+            for (id, task) in tasks {
+                print_task(config, id, task, options.detailed_output, id_column_width);
+            }
         }
     }
 
     Ok(())
 }
 
-fn print_header(detailed_output: bool, id_column_size: usize) {
+/// Returns the tasks that `ls` would list with `args`, in the same order.
+/// Archived tasks have no ID.
+pub fn listed_tasks(
+    todo: &TodoFile,
+    config: &Config,
+    args: &[String],
+) -> Result<Vec<(Option<TaskId>, Task)>, Error> {
+    let options = Options::parse(&expand_views(config, args)?)?;
+    let done = open_done_file(todo, &options)?;
+    Ok(select(todo, &done, config, &options)
+        .into_iter()
+        .map(|(id, task)| (id.copied(), task.clone()))
+        .collect())
+}
+
+/// Reads done.txt if `options` include archived tasks
+fn open_done_file(todo: &TodoFile, options: &Options) -> Result<DoneFile, Error> {
+    match options.show_archived {
+        true => {
+            let path = todo.done_path();
+            DoneFile::open(&path)
+                .map_err(|e| Error::Io(format!("Unable to read {}: {}", path.display(), e)))
+        }
+        false => Ok(DoneFile::default()),
+    }
+}
+
+/// Returns the tasks included by `options` in the order they are listed.
+/// Archived tasks have no ID since they can't be modified.
+fn select<'a>(
+    todo: &'a TodoFile,
+    done: &'a DoneFile,
+    config: &Config,
+    options: &Options,
+) -> Vec<Listed<'a>> {
+    let mut tasks = todo
+        .iter()
+        .map(|(id, task)| (Some(id), task))
+        .chain(done.tasks().map(|task| (None, task)))
+        .filter(|(_, task)| options.includes(task))
+        .collect::<Vec<Listed>>();
+    let order = options.sort.as_ref().unwrap_or(&config.ls_sort.value);
+    match (options.reverse, order.is_empty()) {
+        (true, true) => tasks.reverse(),
+        (true, false) => order.reversed().sort(&mut tasks, |(_, task)| task),
+        (false, _) => order.sort(&mut tasks, |(_, task)| task),
+    }
+    tasks
+}
+
+/// Lists the tasks after a command has changed them. With --json or
+/// --format ndjson, main prints the changes along with all tasks instead,
+/// once they are saved.
+pub fn report(todo: &mut TodoFile, config: &Config, args: &[String]) -> Result<(), Error> {
+    match config.output.value {
+        OutputFormat::Human => run(todo, config, args),
+        _ => Ok(()),
+    }
+}
+
+/// Returns the JSON objects of the tasks changed and removed since
+/// todo.txt was read, and of all tasks
+pub fn json_changes(todo: &TodoFile) -> (Vec<Value>, Vec<Value>, Vec<Value>) {
+    let (changed, removed) = todo.changes();
+    let changed = todo
+        .iter()
+        .filter(|(id, _)| changed.contains(id))
+        .map(|(id, task)| json::task(Some(id), task));
+    let removed = removed.iter().map(|task| json::task(None, task));
+    let tasks = todo.iter().map(|(id, task)| json::task(Some(id), task));
+    (changed.collect(), removed.collect(), tasks.collect())
+}
+
+/// Returns the IDs of the tasks in todo.txt that `ls` would list with
+/// `args`. Archived tasks are never included.
+pub fn matching_ids(
+    todo: &TodoFile,
+    config: &Config,
+    args: &[String],
+) -> Result<Vec<TaskId>, Error> {
+    let options = Options::parse(&expand_views(config, args)?)?;
+    Ok(todo
+        .iter()
+        .filter(|(_, task)| options.includes(task))
+        .map(|(id, _)| *id)
+        .collect())
+}
+
+fn print_header(config: &Config, detailed_output: bool, id_column_size: usize) {
     if detailed_output {
         println!(
-            "  {:>size$} Pri Completed  Created",
+            "  {:>size$} Pri {:width$} {:width$} Due",
             "ID",
-            size = id_column_size
+            "Completed",
+            "Created",
+            size = id_column_size,
+            width = date_column_width(config)
         )
     } else {
         println!("  {:>size$}", "ID", size = id_column_size)
     }
 }
 
-fn print_task(id: &TaskId, task: &Task, detailed_output: bool, id_column_width: usize) {
+fn print_task(
+    config: &Config,
+    id: Option<&TaskId>,
+    task: &Task,
+    detailed_output: bool,
+    id_column_width: usize,
+) {
+    let colors = &config.colors;
     let mut output = match task.completed {
-        true => format!("{:2}", "✔".green()),
+        true => format!("{:2}", "✔".color(colors.completed.value)),
         false => "  ".to_string(),
     };
 
+    let id = id.map(|id| id.to_string()).unwrap_or_default();
     output.push_str(&format!("{:>width$} ", id, width = id_column_width));
 
     if detailed_output {
@@ -71,19 +496,31 @@ fn print_task(id: &TaskId, task: &Task, detailed_output: bool, id_column_width:
             "{:^3} ",
             task.priority.map(|p| p.to_string()).unwrap_or_default()
         ));
-        output.push_str(&format_date(task.completion_date));
-        output.push_str(&format_date(task.creation_date));
+        output.push_str(&format_date_column(config, task.completion_date));
+        output.push_str(&format_date_column(config, task.creation_date));
+        output.push_str(&format_date_column(config, task.due_date()));
     }
 
     let description_width = match detailed_output {
-        true => task.description.len() + 1,
+        true => task.description().len() + 1,
         false => {
             let terminal_width = terminal_size().map(|(Width(w), _)| w).unwrap_or(80);
             (terminal_width as usize) - 3 - id_column_width
         }
     };
 
-    output.push_str(&format_description(&task.description, description_width));
+    // Highlight tasks that need attention
+    let color = match (task.is_overdue(), task.is_due_today()) {
+        (true, _) => colors.overdue.value,
+        (_, true) => colors.due_today.value,
+        _ => colors.description.value,
+    };
+    output.push_str(&format_description(
+        config,
+        task.description(),
+        description_width,
+        color,
+    ));
 
     _ = if task.completed {
         writeln!(io::stdout(), "{}", output.strikethrough())
@@ -92,11 +529,29 @@ fn print_task(id: &TaskId, task: &Task, detailed_output: bool, id_column_width:
     }
 }
 
-fn format_date(date: Option<Date>) -> String {
-    format!("{:10} ", date.map_or(String::new(), |x| x.to_string()))
+/// Dates are at least as wide as the header "Completed". Month names
+/// differ in length, so the widest of the months is used.
+fn date_column_width(config: &Config) -> usize {
+    (1..=12)
+        .filter_map(|month| Date::new(2000, month, 28).ok())
+        .map(|date| format_date(date, &config.date_format.value).chars().count())
+        .fold(9, max)
+}
+
+fn format_date_column(config: &Config, date: Option<Date>) -> String {
+    format!(
+        "{:width$} ",
+        date.map_or(String::new(), |x| format_date(x, &config.date_format.value)),
+        width = date_column_width(config)
+    )
 }
 
-fn format_description(description: &str, available_width: usize) -> String {
+fn format_description(
+    config: &Config,
+    description: &str,
+    available_width: usize,
+    color: Color,
+) -> String {
     // If necessary, truncate desciption to fit terminal width
     let description: String = match description.len() > available_width {
         true => iterate![..description.chars().take(available_width - 1), '…'].collect(),
@@ -107,10 +562,176 @@ fn format_description(description: &str, available_width: usize) -> String {
         .split_whitespace()
         // choose coloring based on first character
         .map(|word| match word.chars().next() {
-            Some('@') => format!("{}", word.yellow()),
-            Some('+') => format!("{}", word.magenta()),
-            _ => format!("{}", word.cyan()),
+            Some('@') => format!("{}", word.color(config.colors.context.value)),
+            Some('+') => format!("{}", word.color(config.colors.project.value)),
+            _ => format!("{}", word.color(color)),
         })
         .collect::<Vec<String>>()
         .join(" ")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(args: &[&str]) -> Options {
+        let args = args.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        Options::parse(&args).unwrap()
+    }
+
+    #[test]
+    fn filter_words() {
+        let task = "Fix the roof +house @home".parse::<Task>().unwrap();
+        assert!(options(&[]).includes(&task));
+        assert!(options(&["ROOF"]).includes(&task));
+        assert!(options(&["+house"]).includes(&task));
+        assert!(!options(&["+hous"]).includes(&task));
+        assert!(!options(&["@office"]).includes(&task));
+    }
+
+    #[test]
+    fn due_date_filters() {
+        let mut task = Task::new("Pay rent");
+        assert!(!options(&["--due-within", "7d"]).includes(&task));
+
+        task.set_due_date(Some(Date::today().add_days(7)));
+        assert!(options(&["--due-within", "7d"]).includes(&task));
+        assert!(!options(&["--due-within", "6d"]).includes(&task));
+        assert!(!options(&["--overdue"]).includes(&task));
+        assert!(!options(&["--due-before", "+7d"]).includes(&task));
+        assert!(options(&["--due-before", "+8d"]).includes(&task));
+    }
+
+    #[test]
+    fn unknown_sort_key() {
+        assert!(Options::parse(&["--sort".to_string(), "size".to_string()]).is_err());
+        assert!(Options::parse(&["--sort".to_string(), "due,-sz".to_string()]).is_err());
+        assert_eq!(
+            options(&["--sort", "pri,-due", "-r"]).sort,
+            Some("priority,-due".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn views_are_expanded() {
+        let mut config = Config::default();
+        config.views.insert(
+            "roof".to_string(),
+            crate::config::Setting {
+                value: "-done  \"the roof\" sort:due".to_string(),
+                source: crate::config::Source::ConfigFile,
+            },
+        );
+        let args = [":roof".to_string(), "-l".to_string()];
+        assert_eq!(
+            expand_views(&config, &args).unwrap(),
+            ["-done", "the roof", "sort:due", "-l"]
+        );
+        assert!(expand_views(&config, &[":attic".to_string()]).is_err());
+
+        let options = Options::parse(&expand_views(&config, &args).unwrap()).unwrap();
+        assert_eq!(options.sort, Some("due".parse().unwrap()));
+        assert!(options.detailed_output);
+        assert!(options.includes(&"Fix the roof".parse::<Task>().unwrap()));
+        assert!(!options.includes(&"x Fix the roof".parse::<Task>().unwrap()));
+    }
+
+    #[test]
+    fn date_column_fits_the_longest_month() {
+        let mut config = Config::default();
+        config.date_format.value = "%e %B %Y".to_string();
+        assert_eq!(date_column_width(&config), "28 September 2000".len());
+        config.date_format.value = "%d.%m.%y".to_string();
+        assert_eq!(date_column_width(&config), "Completed".len());
+    }
+
+    #[test]
+    fn command_line_overrides_configured_flags() {
+        let defaults = options(&["--sort", "due", "-l", "-done"]);
+        let combined = options(&["--sort", "pri", "+home"]).or(defaults);
+        assert_eq!(combined.sort, Some("pri".parse().unwrap()));
+        assert!(combined.detailed_output);
+        assert!(combined.includes(&"Fix the roof +home".parse::<Task>().unwrap()));
+        assert!(!combined.includes(&"x Fix the roof +home".parse::<Task>().unwrap()));
+        assert!(!combined.includes(&"Call Bob +work".parse::<Task>().unwrap()));
+    }
+
+    #[test]
+    fn grouping() {
+        let config = Config::default();
+        let tasks = [
+            "(B) Paint +house +garden +garden",
+            "Call Bob @phone",
+            "(A) Mow +garden due:2021-06-01",
+            "Weed +garden due:2020-06-01",
+        ]
+        .map(|line| line.parse::<Task>().unwrap());
+        let ids = [TaskId(0), TaskId(1), TaskId(2), TaskId(3)];
+        let tasks = ids
+            .iter()
+            .zip(&tasks)
+            .map(|(id, task)| (Some(id), task))
+            .collect::<Vec<_>>();
+        let headings = |config: &Config, field: &str| {
+            field
+                .parse::<Grouping>()
+                .unwrap()
+                .group(config, &tasks)
+                .into_iter()
+                .map(|(heading, group)| {
+                    let ids = group.iter().map(|(id, _)| id.unwrap().to_string());
+                    format!("{}: {}", heading, ids.collect::<Vec<_>>().join(","))
+                })
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(
+            headings(&config, "project"),
+            ["+garden: 1,3,4", "+house: 1", "(none): 2"]
+        );
+        assert_eq!(
+            headings(&config, "priority"),
+            ["(A): 3", "(B): 1", "(none): 2,4"]
+        );
+        assert_eq!(
+            headings(&config, "due"),
+            ["2020-06-01: 4", "2021-06-01: 3", "(none): 1,2"]
+        );
+        assert_eq!(
+            headings(&config, "tag:due"),
+            ["due:2020-06-01: 4", "due:2021-06-01: 3", "(none): 1,2"]
+        );
+
+        // Different dates that look the same are still separate groups
+        let mut config = Config::default();
+        config.date_format.value = "%B".to_string();
+        assert_eq!(
+            headings(&config, "due"),
+            ["June: 4", "June: 3", "(none): 1,2"]
+        );
+        assert!("size".parse::<Grouping>().is_err());
+    }
+
+    #[test]
+    fn future_tasks_are_hidden() {
+        let mut task = Task::new("Renew passport");
+        task.set_threshold_date(Some(Date::today().next()));
+        assert!(!options(&[]).includes(&task));
+        assert!(options(&["--future"]).includes(&task));
+        assert!(options(&["-a"]).includes(&task));
+
+        task.set_threshold_date(Some(Date::today()));
+        assert!(options(&[]).includes(&task));
+    }
+
+    #[test]
+    fn done_shows_only_completed_tasks() {
+        let task = Task::new("Renew passport");
+        let done = "x Pay rent".parse::<Task>().unwrap();
+        assert!(!options(&["--done"]).includes(&task));
+        assert!(options(&["--done"]).includes(&done));
+        assert!(options(&["--done"]).show_archived);
+        assert!(options(&["--all"]).show_archived);
+        assert!(!options(&["--future"]).show_archived);
+    }
+}
@@ -1,74 +1,217 @@
 use strum_macros::{EnumIter, EnumMessage, EnumString};
 
-use crate::error::Error;
-use crate::TodoFile;
+use crate::config::OutputFormat;
+use crate::Config;
+use mama::error::Error;
+use mama::TodoFile;
+
+/// Prints a message for people to read. With --json or --format ndjson,
+/// messages go to stderr so that stdout only has the JSON output.
+macro_rules! say {
+    ($config:expr) => {
+        say!($config, "")
+    };
+    ($config:expr, $($arg:tt)*) => {
+        match $config.output.value {
+            crate::config::OutputFormat::Human => println!($($arg)*),
+            _ => eprintln!($($arg)*),
+        }
+    };
+}
 
 mod add;
+mod append;
+mod archive;
+mod bump;
 mod complete;
+mod config;
+mod depri;
+mod edit;
+mod export;
 mod help;
+mod history;
+mod import;
 mod list;
+pub use list::json_changes;
+mod lower;
+mod prepend;
+mod pri;
+mod redo;
 mod remove;
+mod replace;
+mod snooze;
 mod uncomplete;
 mod undo;
-mod util;
+pub mod util;
+mod view;
+mod views;
 
 #[derive(Debug, EnumIter, PartialEq, EnumString, EnumMessage)]
 pub enum Commands {
     #[strum(serialize = "add")]
     Add,
+    #[strum(serialize = "append")]
+    Append,
+    #[strum(serialize = "archive")]
+    Archive,
+    #[strum(serialize = "bump")]
+    Bump,
     #[strum(serialize = "complete")]
     Complete,
+    #[strum(serialize = "config")]
+    Config,
+    #[strum(serialize = "depri")]
+    Depri,
+    #[strum(serialize = "edit")]
+    Edit,
+    #[strum(serialize = "export")]
+    Export,
     #[strum(serialize = "help")]
     Help,
+    #[strum(serialize = "history")]
+    History,
+    #[strum(serialize = "import")]
+    Import,
+    #[strum(serialize = "lower")]
+    Lower,
     #[strum(serialize = "ls")]
     List,
+    #[strum(serialize = "prepend")]
+    Prepend,
+    #[strum(serialize = "pri")]
+    Pri,
+    #[strum(serialize = "redo")]
+    Redo,
+    #[strum(serialize = "replace")]
+    Replace,
     #[strum(serialize = "rm")]
     Remove,
+    #[strum(serialize = "snooze")]
+    Snooze,
     #[strum(serialize = "uncomplete")]
     Uncomplete,
     #[strum(serialize = "undo")]
     Undo,
+    #[strum(serialize = "view")]
+    View,
+    #[strum(serialize = "views")]
+    Views,
 }
 
 impl Commands {
-    pub fn run(&self, todo: &mut TodoFile, args: &[String]) {
+    /// Runs the command and prints any error. Returns false if it failed.
+    pub fn run(&self, todo: &mut TodoFile, config: &Config, args: &[String]) -> bool {
         let result = match self {
-            Commands::Add => add::run(todo, args),
-            Commands::Complete => complete::run(todo, args),
-            Commands::Help => help::run(todo, args),
-            Commands::List => list::run(todo, args),
-            Commands::Remove => remove::run(todo, args),
-            Commands::Uncomplete => uncomplete::run(todo, args),
-            Commands::Undo => undo::run(todo, args),
+            Commands::Add => add::run(todo, config, args),
+            Commands::Append => append::run(todo, config, args),
+            Commands::Archive => archive::run(todo, config, args),
+            Commands::Bump => bump::run(todo, config, args),
+            Commands::Complete => complete::run(todo, config, args),
+            Commands::Config => config::run(todo, config, args),
+            Commands::Depri => depri::run(todo, config, args),
+            Commands::Edit => edit::run(todo, config, args),
+            Commands::Export => export::run(todo, config, args),
+            Commands::Help => help::run(todo, config, args),
+            Commands::History => history::run(todo, config, args),
+            Commands::Import => import::run(todo, config, args),
+            Commands::Lower => lower::run(todo, config, args),
+            Commands::List => list::run(todo, config, args),
+            Commands::Prepend => prepend::run(todo, config, args),
+            Commands::Pri => pri::run(todo, config, args),
+            Commands::Redo => redo::run(todo, config, args),
+            Commands::Replace => replace::run(todo, config, args),
+            Commands::Remove => remove::run(todo, config, args),
+            Commands::Snooze => snooze::run(todo, config, args),
+            Commands::Uncomplete => uncomplete::run(todo, config, args),
+            Commands::Undo => undo::run(todo, config, args),
+            Commands::View => view::run(todo, config, args),
+            Commands::Views => views::run(todo, config, args),
         };
 
-        if let Some(error) = result.err() {
-            eprintln!("⛔ Error: {}\n", error);
-            self.usage();
+        match (result, config.output.value) {
+            (Ok(()), _) => return true,
+            (Err(error), OutputFormat::Human) => {
+                eprintln!("⛔ Error: {}\n", error);
+                self.usage();
+            }
+            (Err(error), _) => crate::json::print("error", error.to_string().into()),
+        }
+        false
+    }
+
+    /// Returns false for commands that only read the list, including
+    /// `import --dry-run`
+    pub fn modifies_list(&self, args: &[String]) -> bool {
+        if *self == Commands::Import && args.iter().any(|arg| arg == "--dry-run") {
+            return false;
         }
+        !matches!(
+            self,
+            Commands::Config
+                | Commands::Export
+                | Commands::Help
+                | Commands::History
+                | Commands::List
+                | Commands::View
+                | Commands::Views
+        )
     }
 
     pub fn description(&self) -> &'static str {
         match self {
             Commands::Add => add::description(),
+            Commands::Append => append::description(),
+            Commands::Archive => archive::description(),
+            Commands::Bump => bump::description(),
             Commands::Complete => complete::description(),
+            Commands::Config => config::description(),
+            Commands::Depri => depri::description(),
+            Commands::Edit => edit::description(),
+            Commands::Export => export::description(),
             Commands::Help => help::description(),
+            Commands::History => history::description(),
+            Commands::Import => import::description(),
+            Commands::Lower => lower::description(),
             Commands::List => list::description(),
+            Commands::Prepend => prepend::description(),
+            Commands::Pri => pri::description(),
+            Commands::Redo => redo::description(),
+            Commands::Replace => replace::description(),
             Commands::Remove => remove::description(),
+            Commands::Snooze => snooze::description(),
             Commands::Uncomplete => uncomplete::description(),
             Commands::Undo => undo::description(),
+            Commands::View => view::description(),
+            Commands::Views => views::description(),
         }
     }
 
     pub fn usage(&self) -> String {
         match self {
             Commands::Add => add::usage().to_string(),
+            Commands::Append => append::usage().to_string(),
+            Commands::Archive => archive::usage().to_string(),
+            Commands::Bump => bump::usage().to_string(),
             Commands::Complete => complete::usage().to_string(),
+            Commands::Config => config::usage().to_string(),
+            Commands::Depri => depri::usage().to_string(),
+            Commands::Edit => edit::usage().to_string(),
+            Commands::Export => export::usage().to_string(),
             Commands::Help => help::usage(),
+            Commands::History => history::usage().to_string(),
+            Commands::Import => import::usage().to_string(),
+            Commands::Lower => lower::usage().to_string(),
             Commands::List => list::usage().to_string(),
+            Commands::Prepend => prepend::usage().to_string(),
+            Commands::Pri => pri::usage().to_string(),
+            Commands::Redo => redo::usage().to_string(),
+            Commands::Replace => replace::usage().to_string(),
             Commands::Remove => remove::usage().to_string(),
+            Commands::Snooze => snooze::usage().to_string(),
             Commands::Uncomplete => uncomplete::usage().to_string(),
             Commands::Undo => undo::usage().to_string(),
+            Commands::View => view::usage().to_string(),
+            Commands::Views => views::usage().to_string(),
         }
     }
 }
@@ -0,0 +1,26 @@
+use crate::commands::util::change_priorities;
+use crate::Config;
+use mama::error::Error;
+use mama::TaskPriority;
+use mama::TodoFile;
+
+pub fn description() -> &'static str {
+    "Set the priority of a task"
+}
+
+pub fn usage() -> &'static str {
+    "[-y] <TASKS> <PRIORITY>
+    -y, --yes     don't ask for confirmation when a filter matches many tasks
+    TASKS         IDs of tasks (3, 3-7, 2,4,9 or id:k3x9),
+                  or a filter as accepted by ls, e.g. +kitchenmakeover
+    PRIORITY      a letter from A (highest) to Z"
+}
+
+pub fn run(todo: &mut TodoFile, config: &Config, args: &[String]) -> Result<(), Error> {
+    let (priority, ids) = match args.split_last() {
+        Some((priority, ids)) if !ids.is_empty() => (TaskPriority::from_letter(priority)?, ids),
+        _ => return Err(Error::InsufficientArguments),
+    };
+
+    change_priorities(todo, config, ids, "Prioritize", |_| Some(priority))
+}
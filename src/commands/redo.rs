@@ -0,0 +1,55 @@
+use crate::commands::util::parse_count;
+use crate::Config;
+use mama::error::Error;
+use mama::history::Entry;
+use mama::DoneFile;
+use mama::History;
+use mama::TodoFile;
+
+pub fn description() -> &'static str {
+    "Redo a command that was undone"
+}
+
+pub fn usage() -> &'static str {
+    "[N]
+    N             number of commands to redo, 1 by default"
+}
+
+pub fn run(todo: &mut TodoFile, config: &Config, args: &[String]) -> Result<(), Error> {
+    let steps = parse_count(args)?;
+    let io_error = |e| Error::Io(format!("Unable to redo: {}", e));
+
+    let mut history = History::open(&todo.history_dir()).map_err(io_error)?;
+    let redone = (0..steps)
+        .map_while(|_| history.redo())
+        .collect::<Vec<Entry>>();
+    let newest = redone.last().ok_or(Error::NothingToRedo)?;
+
+    let archived = redone
+        .iter()
+        .map(|entry| history.archived(entry))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io_error)?;
+
+    todo.restore(&history.after_path(newest))
+        .map_err(io_error)?;
+    todo.save()?;
+    history.save().map_err(io_error)?;
+
+    // The tasks archived by the redone commands go back to done.txt
+    let mut done = DoneFile::open(&todo.done_path()).map_err(io_error)?;
+    for archived in &archived {
+        done.append(archived).map_err(io_error)?;
+    }
+    for entry in &redone {
+        say!(
+            config,
+            "↷ Reapplied '{}' from {}",
+            entry.command,
+            entry.time()
+        );
+    }
+    say!(config);
+
+    crate::commands::list::report(todo, config, &[])
+}
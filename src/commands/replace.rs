@@ -0,0 +1,54 @@
+use crate::commands::util::id_and_text;
+use crate::Config;
+use mama::error::Error;
+use mama::Task;
+use mama::TodoFile;
+
+pub fn description() -> &'static str {
+    "Replace the text of a task"
+}
+
+pub fn usage() -> &'static str {
+    "<id of task> <new text>
+    The priority, dates and completion status of the task are kept, as is
+    its id: tag unless the new text has one."
+}
+
+pub fn run(todo: &mut TodoFile, config: &Config, args: &[String]) -> Result<(), Error> {
+    let (id, text) = id_and_text(todo, args)?;
+
+    if let Some(task) = todo.modify(id, |task| replace(task, &text)) {
+        say!(config, "✏ Updated task {}, '{}'\n", id, task.description());
+    }
+
+    crate::commands::list::report(todo, config, &[])
+}
+
+/// Replaces the description of `task` with `text`, keeping its stable ID
+fn replace(task: &mut Task, text: &str) {
+    let stable_id = task.tag("id").map(String::from);
+    task.set_description(text);
+    if let (Some(stable_id), None) = (stable_id, task.tag("id")) {
+        task.set_tag("id", &stable_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replacing_keeps_details() {
+        let mut task = "x (A) 2021-06-10 2021-06-09 Old text id:k3x9"
+            .parse::<Task>()
+            .unwrap();
+        replace(&mut task, "New text +project");
+        assert_eq!(
+            task.to_string(),
+            "x (A) 2021-06-10 2021-06-09 New text +project id:k3x9"
+        );
+
+        replace(&mut task, "Newer text id:abcd");
+        assert_eq!(task.tag("id"), Some("abcd"));
+    }
+}
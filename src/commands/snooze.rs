@@ -0,0 +1,41 @@
+use crate::commands::util::targets;
+use crate::Config;
+use mama::date::parse_date;
+use mama::error::Error;
+use mama::TodoFile;
+
+pub fn description() -> &'static str {
+    "Hide a task until a later date"
+}
+
+pub fn usage() -> &'static str {
+    "[-y] <TASKS> <DATE>
+    -y, --yes     don't ask for confirmation when a filter matches many tasks
+    TASKS         IDs of tasks to snooze (3, 3-7, 2,4,9 or id:k3x9),
+                  or a filter as accepted by ls, e.g. +kitchenmakeover
+    DATE          the day the task reappears: YYYY-MM-DD, tomorrow or +N[dw]"
+}
+
+pub fn run(todo: &mut TodoFile, config: &Config, args: &[String]) -> Result<(), Error> {
+    let (date, ids) = match args.split_last() {
+        Some((date, ids)) if !ids.is_empty() => (parse_date(date)?, ids),
+        _ => return Err(Error::InsufficientArguments),
+    };
+
+    let ids = targets(todo, config, ids, "Snooze")?;
+
+    for id in ids {
+        if let Some(task) = todo.modify(id, |task| task.set_threshold_date(Some(date))) {
+            say!(
+                config,
+                "💤 Snoozed task {}, '{}' until {}",
+                id,
+                task.description(),
+                date
+            );
+        }
+    }
+    say!(config);
+
+    crate::commands::list::report(todo, config, &[])
+}
@@ -1,29 +1,33 @@
-use crate::commands::util::{args_to_task_ids, assert_ids_exist};
-use crate::error::Error;
-use crate::TodoFile;
+use crate::commands::util::targets;
+use crate::Config;
+use mama::error::Error;
+use mama::TodoFile;
 
 pub fn description() -> &'static str {
     "Mark a previously finished task as uncompleted"
 }
 
 pub fn usage() -> &'static str {
-    "<id of finished task>..."
+    "[-y] <TASKS>
+    -y, --yes     don't ask for confirmation when a filter matches many tasks
+    TASKS         IDs of finished tasks (3, 3-7, 2,4,9 or id:k3x9),
+                  or a filter as accepted by ls, e.g. --done +kitchenmakeover"
 }
 
-pub fn run(todo: &mut TodoFile, args: &[String]) -> Result<(), Error> {
-    if args.is_empty() {
-        return Err(Error::InsufficientArguments);
-    }
-
-    let ids = args_to_task_ids(args)?;
-    assert_ids_exist(todo, &ids)?;
+pub fn run(todo: &mut TodoFile, config: &Config, args: &[String]) -> Result<(), Error> {
+    let ids = targets(todo, config, args, "Uncomplete")?;
 
     for id in ids {
         if let Some(task) = todo.uncomplete(id) {
-            println!("☐ Marked task {}, '{}' as unfinished", id, task.description);
+            say!(
+                config,
+                "☐ Marked task {}, '{}' as unfinished",
+                id,
+                task.description()
+            );
         }
     }
-    println!();
+    say!(config);
 
-    crate::commands::list::run(todo, &[])
+    crate::commands::list::report(todo, config, &[])
 }
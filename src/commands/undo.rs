@@ -1,24 +1,60 @@
-use crate::commands::Error;
-use crate::TodoFile;
+use crate::commands::util::parse_count;
+use crate::Config;
+use mama::error::Error;
+use mama::history::Entry;
+use mama::DoneFile;
+use mama::History;
+use mama::TodoFile;
 
 pub fn description() -> &'static str {
     "Undo previous command"
 }
 
 pub fn usage() -> &'static str {
-    "" // no arguments
+    "[N]
+    N             number of commands to undo, 1 by default"
 }
 
-pub fn run(todo: &mut TodoFile, _args: &[String]) -> Result<(), Error> {
-    println!("↶ Reverting previous command...\n");
-    let backup_file = TodoFile::undo_path();
-
-    if let Ok(previous) = TodoFile::from(&backup_file) {
-        *todo = previous;
-        todo.save().expect("Error while saving todo.txt");
-        crate::commands::list::run(todo, &[])
-    } else {
-        eprintln!("Error opening {}.", backup_file.to_str().unwrap());
-        std::process::exit(1);
+pub fn run(todo: &mut TodoFile, config: &Config, args: &[String]) -> Result<(), Error> {
+    let steps = parse_count(args)?;
+    let io_error = |e| Error::Io(format!("Unable to undo: {}", e));
+
+    let mut history = History::open(&todo.history_dir()).map_err(io_error)?;
+    let undone = (0..steps)
+        .map_while(|_| history.undo())
+        .collect::<Vec<Entry>>();
+    let oldest = undone.last().ok_or(Error::NothingToUndo)?;
+
+    let archived = undone
+        .iter()
+        .map(|entry| history.archived(entry))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io_error)?;
+
+    todo.restore(&history.before_path(oldest))
+        .map_err(io_error)?;
+    todo.save()?;
+    history.save().map_err(io_error)?;
+
+    // The tasks archived by the undone commands are taken out of done.txt
+    let mut done = DoneFile::open(&todo.done_path()).map_err(io_error)?;
+    for (entry, archived) in undone.iter().zip(&archived) {
+        if !done.remove_appended(archived).map_err(io_error)? {
+            eprintln!(
+                "⚠ done.txt has changed since '{}', the tasks it archived were left there",
+                entry.command
+            );
+        }
+    }
+    for entry in &undone {
+        say!(
+            config,
+            "↶ Reverted '{}' from {}",
+            entry.command,
+            entry.time()
+        );
     }
+    say!(config);
+
+    crate::commands::list::report(todo, config, &[])
 }
@@ -1,11 +1,188 @@
 use crate::commands::Error;
-use crate::{TaskId, TodoFile};
+use crate::Config;
+use mama::{Task, TaskId, TaskPriority, TodoFile};
+use std::io::{self, BufRead, Write};
 
-pub fn args_to_task_ids(args: &[String]) -> Result<Vec<TaskId>, Error> {
-    args.iter()
-        .map(|arg| arg.parse::<TaskId>())
-        .into_iter()
-        .collect()
+/// Parses the tasks referred to on the command line. Each argument is
+/// a number, a range such as `3-7`, a stable identifier such as `id:k3x9`,
+/// or a comma-separated list of these, e.g. `2,4,9`.
+pub fn args_to_task_ids(todo: &TodoFile, args: &[String]) -> Result<Vec<TaskId>, Error> {
+    let mut ids = Vec::new();
+    for part in args.iter().flat_map(|arg| arg.split(',')) {
+        if let Some(stable_id) = part.strip_prefix("id:") {
+            let id = todo
+                .find_stable_id(stable_id)
+                .ok_or_else(|| Error::StableIdNotFound(stable_id.to_string()))?;
+            ids.push(id);
+        } else if let Some((first, last)) = part.split_once('-') {
+            let (first, last) = (first.parse::<TaskId>()?, last.parse::<TaskId>()?);
+            if first > last {
+                return Err(Error::InvalidArgument(format!("empty range '{}'", part)));
+            }
+            // Checked before expanding, so that a huge range fails quickly
+            if !todo.has_id(&last) {
+                return Err(Error::IdNotFound(last));
+            }
+            ids.extend((first.0..=last.0).map(TaskId));
+        } else {
+            ids.push(part.parse::<TaskId>()?);
+        }
+    }
+
+    // Naming a task twice must not act on it twice
+    let mut seen = std::collections::HashSet::new();
+    ids.retain(|id| seen.insert(*id));
+    Ok(ids)
+}
+
+/// Splits `args` into the ID of a single existing task and the text that
+/// follows it, as in `append <id> <text>`
+pub fn id_and_text(todo: &TodoFile, args: &[String]) -> Result<(TaskId, String), Error> {
+    let (id, text) = match args.split_first() {
+        Some((id, text)) if !text.is_empty() => (id, text.join(" ")),
+        _ => return Err(Error::InsufficientArguments),
+    };
+
+    let ids = args_to_task_ids(todo, std::slice::from_ref(id))?;
+    let id = match ids.as_slice() {
+        [id] => *id,
+        _ => return Err(Error::InvalidArgument("expected a single task".to_string())),
+    };
+    assert_ids_exist(todo, &[id])?;
+    Ok((id, text.trim().to_string()))
+}
+
+/// Replaces the priority of each task in `args`, as accepted by `targets`,
+/// with what `change` makes of it. Prints each change and then lists the
+/// tasks.
+pub fn change_priorities(
+    todo: &mut TodoFile,
+    config: &Config,
+    args: &[String],
+    action: &str,
+    change: impl Fn(Option<TaskPriority>) -> Option<TaskPriority>,
+) -> Result<(), Error> {
+    for id in targets(todo, config, args, action)? {
+        let mut previous = None;
+        if let Some(task) = todo.modify(id, |task| {
+            previous = task.priority;
+            task.priority = change(task.priority);
+        }) {
+            print_priority_change(config, id, task, previous);
+        }
+    }
+    say!(config);
+
+    crate::commands::list::report(todo, config, &["-l".to_string()])
+}
+
+/// Prints how the priority of task `id` changed from `previous`
+fn print_priority_change(config: &Config, id: TaskId, task: &Task, previous: Option<TaskPriority>) {
+    let name = |priority: Option<TaskPriority>| match priority {
+        Some(priority) => priority.to_string(),
+        None => "none".to_string(),
+    };
+    match task.priority == previous {
+        true => say!(
+            config,
+            "Task {}, '{}' keeps priority {}",
+            id,
+            task.description(),
+            name(previous)
+        ),
+        false => say!(
+            config,
+            "Task {}, '{}': priority {} → {}",
+            id,
+            task.description(),
+            name(previous),
+            name(task.priority)
+        ),
+    }
+}
+
+/// Returns true if `arg` refers to tasks by their IDs rather than
+/// being a filter
+fn is_id_argument(arg: &str) -> bool {
+    let number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    // A single number or a range N-M, so that a date like 2024-01-01 is
+    // a filter
+    arg.split(',').all(|part| {
+        part.starts_with("id:")
+            || match part.split_once('-') {
+                Some((first, last)) => number(first) && number(last),
+                None => number(part),
+            }
+    })
+}
+
+/// Returns the tasks a command should act on. `args` are either IDs as
+/// accepted by `args_to_task_ids`, or a filter as accepted by `ls`, e.g.
+/// `+kitchenmakeover` or `--done --before 2024-01-01`.
+///
+/// If a filter matches more tasks than the configured confirm_threshold,
+/// the user is asked whether to `action` them, unless `args` contains
+/// -y/--yes.
+pub fn targets(
+    todo: &TodoFile,
+    config: &Config,
+    args: &[String],
+    action: &str,
+) -> Result<Vec<TaskId>, Error> {
+    let mut args = Vec::from(args);
+    let length = args.len();
+    args.retain(|arg| arg != "-y" && arg != "--yes");
+    let confirmed = args.len() < length;
+
+    if args.is_empty() {
+        return Err(Error::InsufficientArguments);
+    }
+    if args.iter().all(|arg| is_id_argument(arg)) {
+        let ids = args_to_task_ids(todo, &args)?;
+        assert_ids_exist(todo, &ids)?;
+        return Ok(ids);
+    }
+
+    let ids = crate::commands::list::matching_ids(todo, config, &args)?;
+    if ids.is_empty() {
+        return Err(Error::NoMatchingTasks);
+    }
+    if ids.len() > config.confirm_threshold.value && !confirmed {
+        for (id, task) in todo.iter().filter(|(id, _)| ids.contains(id)) {
+            say!(config, "{:>4} {}", id, task.description());
+        }
+        say!(config);
+        if !confirm(&format!("{} these {} tasks?", action, ids.len()), false)? {
+            return Err(Error::Cancelled);
+        }
+    }
+    Ok(ids)
+}
+
+/// Asks the user a yes or no `question`. An empty answer is `default`,
+/// and the end of input cancels.
+/// The question goes to stderr, which keeps it out of JSON output.
+pub fn confirm(question: &str, default: bool) -> Result<bool, Error> {
+    match default {
+        true => eprint!("{} [Y/n] ", question),
+        false => eprint!("{} [y/N] ", question),
+    }
+    io::stderr().flush().map_err(|e| Error::Io(e.to_string()))?;
+
+    let mut answer = String::new();
+    let read = io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .map_err(|e| Error::Io(e.to_string()))?;
+    eprintln!();
+    // Nobody is there to answer, so asking again would loop forever
+    if read == 0 {
+        return Err(Error::Cancelled);
+    }
+    Ok(match answer.trim() {
+        "" => default,
+        answer => matches!(answer, "y" | "Y" | "yes"),
+    })
 }
 
 pub fn assert_ids_exist(todo: &TodoFile, ids: &[TaskId]) -> Result<(), Error> {
@@ -15,3 +192,124 @@ pub fn assert_ids_exist(todo: &TodoFile, ids: &[TaskId]) -> Result<(), Error> {
         Ok(())
     }
 }
+
+/// Parses an optional repetition count, e.g. the N in `undo [N]`.
+/// Without arguments, the count is 1.
+pub fn parse_count(args: &[String]) -> Result<usize, Error> {
+    match args {
+        [] => Ok(1),
+        [n] => n
+            .parse::<usize>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| Error::InvalidArgument(format!("'{}' is not a positive number", n))),
+        _ => Err(Error::InvalidArgument(format!("unexpected '{}'", args[1]))),
+    }
+}
+
+/// Removes the first of `flags` and the value following it from `args`,
+/// returning the value
+pub fn take_option(args: &mut Vec<String>, flags: &[&str]) -> Result<Option<String>, Error> {
+    match args.iter().position(|x| flags.contains(&x.as_str())) {
+        Some(index) if index + 1 < args.len() => {
+            args.remove(index);
+            Ok(Some(args.remove(index)))
+        }
+        Some(_) => Err(Error::InsufficientArguments),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_and_stable_ids() {
+        let mut todo = TodoFile::default();
+        todo.add("a id:k3x9".parse().unwrap());
+        todo.add("b".parse().unwrap());
+
+        let args = ["2".to_string(), "id:k3x9".to_string()];
+        assert_eq!(
+            args_to_task_ids(&todo, &args),
+            Ok(vec![TaskId(1), TaskId(0)])
+        );
+        assert_eq!(
+            args_to_task_ids(&todo, &["1,id:k3x9".to_string()]),
+            Ok(vec![TaskId(0)])
+        );
+        assert_eq!(
+            args_to_task_ids(&todo, &["id:nope".to_string()]),
+            Err(Error::StableIdNotFound("nope".to_string()))
+        );
+    }
+
+    #[test]
+    fn ranges_and_lists() {
+        let mut todo = TodoFile::default();
+        for _ in 0..5 {
+            todo.add(Task::new("task"));
+        }
+        let ids = |arg: &str| args_to_task_ids(&todo, &[arg.to_string()]);
+        assert_eq!(ids("3-5"), Ok(vec![TaskId(2), TaskId(3), TaskId(4)]));
+        assert_eq!(ids("2,4,9"), Ok(vec![TaskId(1), TaskId(3), TaskId(8)]));
+        assert_eq!(ids("1-2,2,7"), Ok(vec![TaskId(0), TaskId(1), TaskId(6)]));
+        assert!(ids("5-3").is_err());
+        assert!(ids("3-").is_err());
+        assert_eq!(ids("4-6"), Err(Error::IdNotFound(TaskId(5))));
+        assert_eq!(
+            ids("1-99999999999"),
+            Err(Error::IdNotFound(TaskId(99999999998)))
+        );
+    }
+
+    #[test]
+    fn ids_are_told_apart_from_filters() {
+        assert!(is_id_argument("3"));
+        assert!(is_id_argument("3-7"));
+        assert!(is_id_argument("2,4,id:k3x9"));
+        assert!(!is_id_argument("+kitchenmakeover"));
+        assert!(!is_id_argument("--done"));
+        assert!(!is_id_argument("3d"));
+        assert!(!is_id_argument("3-"));
+        assert!(!is_id_argument("2024-01-01"));
+        assert!(!is_id_argument("1,2024-01-01"));
+        assert!(!is_id_argument(""));
+    }
+
+    #[test]
+    fn filters_select_targets() {
+        let mut todo = TodoFile::default();
+        todo.add("Paint walls +kitchen".parse().unwrap());
+        todo.add("x 2023-05-01 2023-04-01 Old task".parse().unwrap());
+        todo.add("Tile floor +kitchen".parse().unwrap());
+        let config = Config::default();
+        let targets = |args: &[&str]| {
+            let args = args.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+            targets(&todo, &config, &args, "Modify")
+        };
+
+        assert_eq!(targets(&["+kitchen"]), Ok(vec![TaskId(0), TaskId(2)]));
+        assert_eq!(
+            targets(&["--done", "--before", "2024-01-01"]),
+            Ok(vec![TaskId(1)])
+        );
+        assert_eq!(targets(&["+garden"]), Err(Error::NoMatchingTasks));
+        assert_eq!(targets(&["4"]), Err(Error::IdNotFound(TaskId(3))));
+    }
+
+    #[test]
+    fn options_with_values() {
+        let mut args = vec!["a".to_string(), "--due".to_string(), "today".to_string()];
+        assert_eq!(
+            take_option(&mut args, &["--due", "-d"]),
+            Ok(Some("today".to_string()))
+        );
+        assert_eq!(args, vec!["a".to_string()]);
+        assert_eq!(take_option(&mut args, &["--due", "-d"]), Ok(None));
+
+        let mut args = vec!["-d".to_string()];
+        assert!(take_option(&mut args, &["--due", "-d"]).is_err());
+    }
+}
@@ -0,0 +1,18 @@
+use crate::Config;
+use mama::error::Error;
+use mama::TodoFile;
+
+pub fn description() -> &'static str {
+    "List the tasks of a view defined in the configuration"
+}
+
+pub fn usage() -> &'static str {
+    "<NAME> [<LS ARGUMENTS>]
+    Same as ls :NAME, see `mama views` for the defined views"
+}
+
+pub fn run(todo: &mut TodoFile, config: &Config, args: &[String]) -> Result<(), Error> {
+    let (name, args) = args.split_first().ok_or(Error::InsufficientArguments)?;
+    let view = format!(":{}", name.trim_start_matches(':'));
+    crate::commands::list::run(todo, config, &[&[view], args].concat())
+}
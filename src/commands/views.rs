@@ -0,0 +1,41 @@
+use crate::config::OutputFormat;
+use crate::json;
+use crate::Config;
+use mama::error::Error;
+use mama::TodoFile;
+
+pub fn description() -> &'static str {
+    "Show the views defined in the configuration"
+}
+
+pub fn usage() -> &'static str {
+    "" // no arguments
+}
+
+pub fn run(_todo: &mut TodoFile, config: &Config, _args: &[String]) -> Result<(), Error> {
+    if config.output.value != OutputFormat::Human {
+        let views = config
+            .views
+            .iter()
+            .map(|(name, view)| (name.clone(), view.value.clone().into()));
+        json::print("views", serde_json::Value::Object(views.collect()));
+        return Ok(());
+    }
+
+    if config.views.is_empty() {
+        println!("No views defined. Add them to the [views] section of the configuration file.");
+        return Ok(());
+    }
+
+    let name_width = config
+        .views
+        .keys()
+        .map(|name| name.len())
+        .max()
+        .unwrap_or(0);
+    for (name, view) in &config.views {
+        println!(":{:width$}  {}", name, view.value, width = name_width);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,613 @@
+use colored::Color;
+use mama::error::Error;
+use mama::sort::{SortOrder, UnknownSortField};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Where the value of a setting comes from
+#[derive(Clone, Debug, PartialEq)]
+pub enum Source {
+    Default,
+    ConfigFile,
+    Environment(&'static str),
+    CommandLine,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::ConfigFile => write!(f, "config file"),
+            Source::Environment(name) => write!(f, "environment variable {}", name),
+            Source::CommandLine => write!(f, "command line"),
+        }
+    }
+}
+
+/// The value of a setting along with where it was set
+#[derive(Clone, Debug, PartialEq)]
+pub struct Setting<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+impl<T> Setting<T> {
+    fn default(value: T) -> Self {
+        Setting {
+            value,
+            source: Source::Default,
+        }
+    }
+
+    fn set(&mut self, value: T, source: Source) {
+        self.value = value;
+        self.source = source;
+    }
+}
+
+/// Colors used when listing tasks
+#[derive(Clone, Debug, PartialEq)]
+pub struct Colors {
+    pub description: Setting<Color>,
+    pub project: Setting<Color>,
+    pub context: Setting<Color>,
+    pub overdue: Setting<Color>,
+    pub due_today: Setting<Color>,
+    pub completed: Setting<Color>,
+}
+
+/// How commands print their results
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    /// Colored text for people to read
+    Human,
+    /// A single JSON document, see `crate::json`
+    Json,
+    /// One JSON document per line
+    Ndjson,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Human),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            _ => Err(Error::InvalidArgument(format!(
+                "unknown format '{}', expected text, json or ndjson",
+                s
+            ))),
+        }
+    }
+}
+
+/// Settings of mama. The defaults can be changed in the configuration
+/// file, e.g. ~/.config/mama/config.toml:
+///
+/// ```toml
+/// todo_file = "~/Sync/todo.txt"
+/// default_command = "ls -l"
+/// date_format = "%d.%m.%Y"
+/// auto_archive = true
+/// stable_ids = true
+/// backup_retention = 10
+/// lock_timeout = 10
+/// confirm_threshold = 5
+///
+/// [ls]
+/// flags = ["--long"]
+/// sort = "priority,due"
+///
+/// [views]
+/// today = "due<=today -done @work sort:pri"
+///
+/// [colors]
+/// description = "cyan"
+/// project = "magenta"
+/// context = "yellow"
+/// overdue = "red"
+/// due_today = "bright yellow"
+/// completed = "green"
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    /// Location of todo.txt
+    pub todo_file: Setting<PathBuf>,
+    /// Command and arguments run when mama is called without a command
+    pub default_command: Setting<Vec<String>>,
+    /// Arguments always passed to `ls`
+    pub ls_flags: Setting<Vec<String>>,
+    /// Order of tasks listed by `ls` unless --sort is given
+    pub ls_sort: Setting<SortOrder>,
+    /// Named `ls` arguments, listed with `ls :name`
+    pub views: BTreeMap<String, Setting<String>>,
+    /// strftime-style format of dates in listings
+    pub date_format: Setting<String>,
+    /// Whether completed tasks are moved to done.txt right away
+    pub auto_archive: Setting<bool>,
+    /// Whether added tasks get an `id:` tag that doesn't change when
+    /// other tasks are removed
+    pub stable_ids: Setting<bool>,
+    /// Number of saves kept in the undo history
+    pub backup_retention: Setting<usize>,
+    /// Seconds to wait for another mama modifying the same todo.txt
+    pub lock_timeout: Setting<u64>,
+    /// Commands ask for confirmation before modifying more tasks than
+    /// this selected with a filter
+    pub confirm_threshold: Setting<usize>,
+    pub colors: Colors,
+    /// Format of the output, chosen on the command line with --json or
+    /// --format
+    pub output: Setting<OutputFormat>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            todo_file: Setting::default(
+                dirs::home_dir()
+                    .expect("Unable to determine home directory.")
+                    .join("todo.txt"),
+            ),
+            default_command: Setting::default(vec!["ls".to_string()]),
+            ls_flags: Setting::default(Vec::new()),
+            ls_sort: Setting::default(SortOrder::default()),
+            views: BTreeMap::new(),
+            date_format: Setting::default("%Y-%m-%d".to_string()),
+            auto_archive: Setting::default(false),
+            stable_ids: Setting::default(false),
+            backup_retention: Setting::default(10),
+            lock_timeout: Setting::default(10),
+            confirm_threshold: Setting::default(5),
+            colors: Colors {
+                description: Setting::default(Color::Cyan),
+                project: Setting::default(Color::Magenta),
+                context: Setting::default(Color::Yellow),
+                overdue: Setting::default(Color::Red),
+                due_today: Setting::default(Color::BrightYellow),
+                completed: Setting::default(Color::Green),
+            },
+            output: Setting::default(OutputFormat::Human),
+        }
+    }
+}
+
+impl Config {
+    /// Reads the configuration file, if there is one, and applies the
+    /// environment variables, the todo.txt location given on the
+    /// command line with -f/--file and the output format given with
+    /// --json or --format.
+    pub fn load(file: Option<&str>, output: Option<OutputFormat>) -> Result<Self, Error> {
+        let mut config = Self::default();
+
+        if let Some(path) = Self::path() {
+            match std::fs::read_to_string(&path) {
+                Ok(contents) => config
+                    .apply(&contents)
+                    .map_err(|e| Error::InvalidConfig(format!("{}: {}", path.display(), e)))?,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+                Err(e) => {
+                    return Err(Error::Io(format!(
+                        "Unable to read {}: {}",
+                        path.display(),
+                        e
+                    )))
+                }
+            }
+        }
+
+        config.resolve_todo_file(file, |name| std::env::var(name).ok());
+        if let Some(output) = output {
+            config.output.set(output, Source::CommandLine);
+        }
+        Ok(config)
+    }
+
+    /// Location of the configuration file, e.g. ~/.config/mama/config.toml
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("mama").join("config.toml"))
+    }
+
+    /// Applies the settings in the contents of a configuration file
+    fn apply(&mut self, contents: &str) -> Result<(), String> {
+        let table = contents
+            .parse::<toml::Table>()
+            .map_err(|e| e.message().trim().to_string())?;
+
+        for (key, value) in flatten(table) {
+            let source = Source::ConfigFile;
+            match key.as_str() {
+                "todo_file" => self
+                    .todo_file
+                    .set(expand_home(&string(&key, value)?), source),
+                "default_command" => {
+                    let command = string(&key, value)?
+                        .split_whitespace()
+                        .map(String::from)
+                        .collect::<Vec<String>>();
+                    if command.is_empty() {
+                        return Err("default_command must not be empty".to_string());
+                    }
+                    self.default_command.set(command, source)
+                }
+                "ls.flags" => self.ls_flags.set(strings(&key, value)?, source),
+                "ls.sort" => match string(&key, value)?.parse() {
+                    Ok(order) => self.ls_sort.set(order, source),
+                    Err(UnknownSortField(field)) => {
+                        return Err(format!("{} has unknown sort key '{}'", key, field))
+                    }
+                },
+                "date_format" => self.date_format.set(string(&key, value)?, source),
+                "auto_archive" => match value {
+                    toml::Value::Boolean(b) => self.auto_archive.set(b, source),
+                    _ => return Err(format!("{} must be true or false", key)),
+                },
+                "stable_ids" => match value {
+                    toml::Value::Boolean(b) => self.stable_ids.set(b, source),
+                    _ => return Err(format!("{} must be true or false", key)),
+                },
+                "backup_retention" => match value {
+                    toml::Value::Integer(n) if n >= 0 => {
+                        self.backup_retention.set(n as usize, source)
+                    }
+                    _ => return Err(format!("{} must be a non-negative integer", key)),
+                },
+                "lock_timeout" => match value {
+                    toml::Value::Integer(n) if n >= 0 => self.lock_timeout.set(n as u64, source),
+                    _ => return Err(format!("{} must be a non-negative integer", key)),
+                },
+                "confirm_threshold" => match value {
+                    toml::Value::Integer(n) if n >= 0 => {
+                        self.confirm_threshold.set(n as usize, source)
+                    }
+                    _ => return Err(format!("{} must be a non-negative integer", key)),
+                },
+                "colors.description" => self.colors.description.set(color(&key, value)?, source),
+                "colors.project" => self.colors.project.set(color(&key, value)?, source),
+                "colors.context" => self.colors.context.set(color(&key, value)?, source),
+                "colors.overdue" => self.colors.overdue.set(color(&key, value)?, source),
+                "colors.due_today" => self.colors.due_today.set(color(&key, value)?, source),
+                "colors.completed" => self.colors.completed.set(color(&key, value)?, source),
+                _ if key.starts_with("views.") => {
+                    let view = Setting {
+                        value: string(&key, value)?,
+                        source,
+                    };
+                    self.views.insert(key["views.".len()..].to_string(), view);
+                }
+                _ => return Err(format!("unknown setting '{}'", key)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolves the location of todo.txt. In order of precedence, it is
+    /// 1. `file`, given on the command line with -f/--file
+    /// 2. the TODO_FILE environment variable
+    /// 3. todo.txt in the directory named by the TODO_DIR environment variable
+    /// 4. `todo_file` in the configuration file
+    /// 5. todo.txt in the home directory
+    fn resolve_todo_file<F>(&mut self, file: Option<&str>, env: F)
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let non_empty = |name| env(name).filter(|value| !value.is_empty());
+
+        if let Some(file) = file {
+            self.todo_file.set(PathBuf::from(file), Source::CommandLine);
+        } else if let Some(file) = non_empty("TODO_FILE") {
+            self.todo_file
+                .set(PathBuf::from(file), Source::Environment("TODO_FILE"));
+        } else if let Some(dir) = non_empty("TODO_DIR") {
+            self.todo_file.set(
+                Path::new(&dir).join("todo.txt"),
+                Source::Environment("TODO_DIR"),
+            );
+        }
+    }
+
+    /// Returns the name, value and source of every setting, with the values
+    /// formatted as they would be written in the configuration file
+    pub fn entries(&self) -> Vec<(String, String, &Source)> {
+        let quote = |s: &str| format!("{:?}", s);
+        let list = |v: &[String]| {
+            let items = v.iter().map(|s| quote(s)).collect::<Vec<String>>();
+            format!("[{}]", items.join(", "))
+        };
+        let color = |c: &Setting<Color>| quote(&color_name(c.value));
+
+        let settings = vec![
+            (
+                "todo_file",
+                quote(&self.todo_file.value.to_string_lossy()),
+                &self.todo_file.source,
+            ),
+            (
+                "default_command",
+                quote(&self.default_command.value.join(" ")),
+                &self.default_command.source,
+            ),
+            (
+                "date_format",
+                quote(&self.date_format.value),
+                &self.date_format.source,
+            ),
+            (
+                "auto_archive",
+                self.auto_archive.value.to_string(),
+                &self.auto_archive.source,
+            ),
+            (
+                "stable_ids",
+                self.stable_ids.value.to_string(),
+                &self.stable_ids.source,
+            ),
+            (
+                "backup_retention",
+                self.backup_retention.value.to_string(),
+                &self.backup_retention.source,
+            ),
+            (
+                "lock_timeout",
+                self.lock_timeout.value.to_string(),
+                &self.lock_timeout.source,
+            ),
+            (
+                "confirm_threshold",
+                self.confirm_threshold.value.to_string(),
+                &self.confirm_threshold.source,
+            ),
+            (
+                "ls.flags",
+                list(&self.ls_flags.value),
+                &self.ls_flags.source,
+            ),
+            (
+                "ls.sort",
+                quote(&self.ls_sort.value.to_string()),
+                &self.ls_sort.source,
+            ),
+            (
+                "colors.description",
+                color(&self.colors.description),
+                &self.colors.description.source,
+            ),
+            (
+                "colors.project",
+                color(&self.colors.project),
+                &self.colors.project.source,
+            ),
+            (
+                "colors.context",
+                color(&self.colors.context),
+                &self.colors.context.source,
+            ),
+            (
+                "colors.overdue",
+                color(&self.colors.overdue),
+                &self.colors.overdue.source,
+            ),
+            (
+                "colors.due_today",
+                color(&self.colors.due_today),
+                &self.colors.due_today.source,
+            ),
+            (
+                "colors.completed",
+                color(&self.colors.completed),
+                &self.colors.completed.source,
+            ),
+        ];
+
+        let views = self
+            .views
+            .iter()
+            .map(|(name, view)| (format!("views.{}", name), quote(&view.value), &view.source));
+        settings
+            .into_iter()
+            .map(|(key, value, source)| (key.to_string(), value, source))
+            .chain(views)
+            .collect()
+    }
+}
+
+/// Turns `[section] key = value` into `("section.key", value)` pairs
+fn flatten(table: toml::Table) -> Vec<(String, toml::Value)> {
+    let mut entries = Vec::new();
+    for (key, value) in table {
+        match value {
+            toml::Value::Table(section) => entries.extend(
+                section
+                    .into_iter()
+                    .map(|(k, v)| (format!("{}.{}", key, k), v)),
+            ),
+            value => entries.push((key, value)),
+        }
+    }
+    entries
+}
+
+fn string(key: &str, value: toml::Value) -> Result<String, String> {
+    match value {
+        toml::Value::String(s) => Ok(s),
+        _ => Err(format!("{} must be a string", key)),
+    }
+}
+
+fn strings(key: &str, value: toml::Value) -> Result<Vec<String>, String> {
+    let error = || format!("{} must be a list of strings", key);
+    match value {
+        toml::Value::Array(values) => values
+            .into_iter()
+            .map(|v| match v {
+                toml::Value::String(s) => Ok(s),
+                _ => Err(error()),
+            })
+            .collect(),
+        _ => Err(error()),
+    }
+}
+
+fn color(key: &str, value: toml::Value) -> Result<Color, String> {
+    let name = string(key, value)?;
+    name.parse::<Color>()
+        .map_err(|_| format!("{} has unknown color '{}'", key, name))
+}
+
+/// The inverse of `Color::from_str`. `Color` isn't matched exhaustively,
+/// since newer versions of colored add variants.
+fn color_name(color: Color) -> String {
+    const NAMES: [&str; 16] = [
+        "black",
+        "red",
+        "green",
+        "yellow",
+        "blue",
+        "magenta",
+        "cyan",
+        "white",
+        "bright black",
+        "bright red",
+        "bright green",
+        "bright yellow",
+        "bright blue",
+        "bright magenta",
+        "bright cyan",
+        "bright white",
+    ];
+    if let Some(name) = NAMES.iter().find(|name| name.parse() == Ok(color)) {
+        return name.to_string();
+    }
+    match color {
+        Color::TrueColor { r, g, b } => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        color => format!("{:?}", color).to_lowercase(),
+    }
+}
+
+/// Replaces a leading "~/" in `path` with the home directory
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    fn parse(contents: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+        config.apply(contents).map(|_| config)
+    }
+
+    #[test]
+    fn empty_file_gives_defaults() {
+        assert_eq!(parse(""), Ok(Config::default()));
+    }
+
+    #[test]
+    fn parse_settings() {
+        let config = parse(
+            "todo_file = \"/srv/todo.txt\"
+            default_command = \"ls  -l\"
+            auto_archive = true
+            stable_ids = true
+            backup_retention = 3
+            lock_timeout = 0
+            [ls]
+            flags = [\"--sort\", \"due\"]
+            sort = \"pri,-created\"
+            [views]
+            today = \"due<=today sort:pri\"
+            [colors]
+            overdue = \"Bright Red\"",
+        )
+        .unwrap();
+        assert_eq!(config.todo_file.value, PathBuf::from("/srv/todo.txt"));
+        assert_eq!(config.todo_file.source, Source::ConfigFile);
+        assert_eq!(config.default_command.value, ["ls", "-l"]);
+        assert!(config.auto_archive.value);
+        assert!(config.stable_ids.value);
+        assert_eq!(config.backup_retention.value, 3);
+        assert_eq!(config.lock_timeout.value, 0);
+        assert_eq!(config.ls_flags.value, ["--sort", "due"]);
+        assert_eq!(config.ls_sort.value.to_string(), "priority,-created");
+        assert_eq!(config.views["today"].value, "due<=today sort:pri");
+        assert_eq!(config.colors.overdue.value, Color::BrightRed);
+        assert_eq!(config.colors.project.source, Source::Default);
+    }
+
+    #[test]
+    fn invalid_settings() {
+        assert!(parse("todo_file = 3").is_err());
+        assert!(parse("todo_file = ").is_err());
+        assert!(parse("default_command = \" \"").is_err());
+        assert!(parse("backup_retention = -1").is_err());
+        assert!(parse("auto_archive = \"yes\"").is_err());
+        assert!(parse("[colors]\nproject = \"plaid\"").is_err());
+        assert!(parse("[ls]\nflags = [1]").is_err());
+        assert!(parse("[ls]\nsort = \"size\"").is_err());
+        assert!(parse("[views]\ntoday = [\"due<=today\"]").is_err());
+        assert_eq!(
+            parse("colour = \"red\""),
+            Err("unknown setting 'colour'".to_string())
+        );
+    }
+
+    #[test]
+    fn color_names_round_trip() {
+        for name in ["black", "magenta", "bright white", "bright yellow"] {
+            assert_eq!(color_name(name.parse::<Color>().unwrap()), name);
+        }
+        let orange = Color::TrueColor {
+            r: 255,
+            g: 128,
+            b: 0,
+        };
+        assert_eq!(color_name(orange), "#ff8000");
+    }
+
+    #[test]
+    fn command_line_takes_precedence() {
+        let mut config = parse("todo_file = \"config.txt\"").unwrap();
+        let vars = [("TODO_FILE", "env.txt"), ("TODO_DIR", "dir")];
+
+        config.resolve_todo_file(None, env(&[("TODO_FILE", "")]));
+        assert_eq!(config.todo_file.value, PathBuf::from("config.txt"));
+
+        config.resolve_todo_file(None, env(&vars[1..]));
+        assert_eq!(
+            config.todo_file.value,
+            PathBuf::from("dir").join("todo.txt")
+        );
+        assert_eq!(config.todo_file.source, Source::Environment("TODO_DIR"));
+
+        config.resolve_todo_file(None, env(&vars));
+        assert_eq!(config.todo_file.value, PathBuf::from("env.txt"));
+
+        config.resolve_todo_file(Some("flag.txt"), env(&vars));
+        assert_eq!(config.todo_file.value, PathBuf::from("flag.txt"));
+        assert_eq!(config.todo_file.source, Source::CommandLine);
+    }
+
+    #[test]
+    fn home_directory_is_the_default() {
+        let mut config = Config::default();
+        config.resolve_todo_file(None, env(&[]));
+        assert_eq!(
+            config.todo_file.value,
+            dirs::home_dir().unwrap().join("todo.txt")
+        );
+    }
+}
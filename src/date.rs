@@ -0,0 +1,163 @@
+use crate::error::Error;
+use gregorian::Date;
+
+/// Parses a date given on the command line. In addition to YYYY-MM-DD,
+/// accepts "today", "tomorrow", "yesterday" and dates relative to today
+/// such as "+3d" or "+2w".
+pub fn parse_date(s: &str) -> Result<Date, Error> {
+    let today = Date::today();
+    match s {
+        "today" => Ok(today),
+        "tomorrow" => Ok(today.next()),
+        "yesterday" => Ok(today.prev()),
+        _ => match s.strip_prefix('+') {
+            Some(period) => parse_period(period).map(|days| today.add_days(days)),
+            None => s
+                .parse::<Date>()
+                .map_err(|_| Error::InvalidDate(s.to_string())),
+        },
+    }
+}
+
+/// Returns the number of seconds the local time zone is ahead of UTC at
+/// `timestamp`, in seconds since the Unix epoch, or 0 if the time zone
+/// can't be determined
+pub fn utc_offset(timestamp: i64) -> i64 {
+    let time = timestamp as libc::time_t;
+    // SAFETY: tm is plain data, filled in by localtime_r or localtime_s
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    #[cfg(not(target_os = "windows"))]
+    let ok = unsafe { !libc::localtime_r(&time, &mut tm).is_null() };
+    #[cfg(target_os = "windows")]
+    let ok = unsafe { libc::localtime_s(&mut tm, &time) == 0 };
+
+    let date = Date::new(
+        (tm.tm_year + 1900) as i16,
+        (tm.tm_mon + 1) as u8,
+        tm.tm_mday as u8,
+    );
+    match (ok, date) {
+        (true, Ok(date)) => {
+            let seconds = tm.tm_hour as i64 * 3600 + tm.tm_min as i64 * 60 + tm.tm_sec as i64;
+            date.to_unix_timestamp() + seconds - timestamp
+        }
+        _ => 0,
+    }
+}
+
+/// Returns the date in the local time zone at `timestamp`
+pub fn local_date(timestamp: i64) -> Date {
+    Date::from_unix_timestamp(timestamp + utc_offset(timestamp))
+}
+
+/// Returns the timestamp at which `date` starts in the local time zone
+pub fn local_midnight(date: Date) -> i64 {
+    let midnight = date.to_unix_timestamp();
+    midnight - utc_offset(midnight)
+}
+
+/// Parses a number of days ("7d") or weeks ("2w") and returns it in days
+pub fn parse_period(s: &str) -> Result<i32, Error> {
+    let invalid = || Error::InvalidDate(s.to_string());
+    let (number, multiplier) = match s.chars().last() {
+        Some('d') => (&s[..s.len() - 1], 1),
+        Some('w') => (&s[..s.len() - 1], 7),
+        _ => return Err(invalid()),
+    };
+    number
+        .parse::<i32>()
+        .map(|n| n * multiplier)
+        .map_err(|_| invalid())
+}
+
+/// Formats `date` according to `format`, which may contain the strftime
+/// conversions %Y, %y, %m, %d, %e, %b, %B and %%
+pub fn format_date(date: Date, format: &str) -> String {
+    const MONTHS: [&str; 12] = [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ];
+    let month = MONTHS[date.month().to_number() as usize - 1];
+
+    let mut result = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => result.push_str(&date.year().to_string()),
+            Some('y') => result.push_str(&format!("{:02}", date.year().to_number() % 100)),
+            Some('m') => result.push_str(&format!("{:02}", date.month().to_number())),
+            Some('d') => result.push_str(&format!("{:02}", date.day())),
+            Some('e') => result.push_str(&format!("{:2}", date.day())),
+            Some('b') => result.push_str(&month[..3]),
+            Some('B') => result.push_str(month),
+            Some('%') => result.push('%'),
+            Some(other) => {
+                result.push('%');
+                result.push(other);
+            }
+            None => result.push('%'),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_dates() {
+        assert_eq!(parse_date("today"), Ok(Date::today()));
+        assert_eq!(parse_date("+1d"), Ok(Date::today().next()));
+        assert_eq!(parse_date("+2w"), Ok(Date::today().add_days(14)));
+    }
+
+    #[test]
+    fn local_dates_are_at_most_a_day_off() {
+        // Local time zones are within a day of UTC
+        let noon = Date::new(2024, 1, 10).unwrap().to_unix_timestamp() + 12 * 3600;
+        let date = local_date(noon);
+        assert!([9, 10, 11].contains(&date.day()), "{}", date);
+
+        let date = Date::new(2024, 1, 10).unwrap();
+        assert_eq!(local_date(local_midnight(date)), date);
+    }
+
+    #[test]
+    fn absolute_dates() {
+        assert_eq!(parse_date("2021-06-09"), Ok(Date::new(2021, 6, 9).unwrap()));
+        assert!(parse_date("2021-06-31").is_err());
+        assert!(parse_date("soon").is_err());
+    }
+
+    #[test]
+    fn date_formats() {
+        let date = Date::new(2021, 6, 9).unwrap();
+        assert_eq!(format_date(date, "%Y-%m-%d"), "2021-06-09");
+        assert_eq!(format_date(date, "%d.%m.%y"), "09.06.21");
+        assert_eq!(format_date(date, "%e %b %Y"), " 9 Jun 2021");
+        assert_eq!(format_date(date, "%B %%%q"), "June %%q");
+    }
+
+    #[test]
+    fn periods() {
+        assert_eq!(parse_period("7d"), Ok(7));
+        assert_eq!(parse_period("3w"), Ok(21));
+        assert!(parse_period("3").is_err());
+        assert!(parse_period("w").is_err());
+    }
+}
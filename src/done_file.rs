@@ -0,0 +1,93 @@
+use crate::Task;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// DoneFile is done.txt, the archive of completed tasks that is kept
+/// in the same directory as todo.txt. Archived tasks are read-only,
+/// new tasks are only ever appended to the file.
+#[derive(Default)]
+pub struct DoneFile {
+    path: PathBuf,
+    tasks: Vec<Task>,
+}
+
+impl DoneFile {
+    /// Reads the done.txt at `path`. A missing file has no tasks.
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let tasks = match std::fs::read_to_string(path) {
+            Ok(contents) => contents
+                .lines()
+                .filter_map(|s| s.parse::<Task>().ok())
+                .collect(),
+            Err(x) if x.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(x) => return Err(x),
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            tasks,
+        })
+    }
+
+    /// Returns an iterator over the archived tasks.
+    pub fn tasks(&self) -> impl Iterator<Item = &Task> {
+        self.tasks.iter()
+    }
+
+    /// Appends `lines`, the text of archived tasks, to the end of done.txt,
+    /// creating the file if needed
+    pub fn append(&mut self, lines: &[String]) -> std::io::Result<()> {
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        let path = &self.path;
+        // Don't glue the first task onto an unterminated last line
+        let needs_newline = std::fs::read(path)
+            .map(|contents| !contents.is_empty() && !contents.ends_with(b"\n"))
+            .unwrap_or(false);
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)?;
+        if needs_newline {
+            writeln!(file)?;
+        }
+        lines
+            .iter()
+            .try_for_each(|line| writeln!(file, "{}", line))?;
+        self.tasks
+            .extend(lines.iter().filter_map(|line| line.parse::<Task>().ok()));
+        Ok(())
+    }
+
+    /// Removes `lines` from the end of done.txt, undoing `append`. Returns
+    /// false, leaving the file as it is, if done.txt does not end with them,
+    /// e.g. because it has been edited since.
+    pub fn remove_appended(&mut self, lines: &[String]) -> std::io::Result<bool> {
+        if lines.is_empty() {
+            return Ok(true);
+        }
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(x) if x.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(x) => return Err(x),
+        };
+        let appended = lines
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect::<String>();
+        let Some(kept) = contents.strip_suffix(&appended) else {
+            return Ok(false);
+        };
+        std::fs::write(&self.path, kept)?;
+        *self = Self::open(&self.path)?;
+        Ok(true)
+    }
+
+    /// Location of done.txt
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
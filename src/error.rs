@@ -1,14 +1,43 @@
 use crate::task_priority::PriorityError;
 use crate::TaskId;
 
+/// Errors of mama. Displaying an error gives a message for the user.
 #[derive(Debug, PartialEq)]
 pub enum Error {
+    /// A required argument is missing
     InsufficientArguments,
+    /// An argument is invalid, with an explanation
     InvalidArgument(String),
+    /// No task has the ID
     IdNotFound(TaskId),
+    /// No task is tagged with `id:` and the value
+    StableIdNotFound(String),
+    /// A filter matches no tasks
+    NoMatchingTasks,
+    /// The user declined to confirm a change
+    Cancelled,
+    /// Task IDs start at 1
     ZeroId,
+    /// A task ID is not a number
     NonnumericId,
+    /// A priority is not a letter from A to Z
     InvalidPriority,
+    /// The date cannot be parsed
+    InvalidDate(String),
+    /// A filter is invalid, with an explanation
+    InvalidFilter(String),
+    /// Reading or writing a file failed, with a message naming the file
+    Io(String),
+    /// The configuration file is invalid, with an explanation
+    InvalidConfig(String),
+    /// The undo history is empty
+    NothingToUndo,
+    /// No change has been undone since the last change
+    NothingToRedo,
+    /// The file at the path was changed by someone else in a conflicting way
+    ConcurrentModification(String),
+    /// Another process holds the lock of the file at the path
+    Locked(String),
 }
 
 impl std::fmt::Display for Error {
@@ -19,13 +48,40 @@ impl std::fmt::Display for Error {
                 write!(f, "Invalid argument: {}", explanation)
             }
             Error::IdNotFound(id) => write!(f, "ID '{}' does not exist.", id),
+            Error::StableIdNotFound(id) => write!(f, "No task is tagged with 'id:{}'.", id),
+            Error::NoMatchingTasks => write!(f, "No tasks match the filter."),
+            Error::Cancelled => write!(f, "Cancelled, no tasks were modified."),
             Error::ZeroId => write!(f, "IDs must be non-zero."),
             Error::NonnumericId => write!(f, "IDs must be numeric."),
             Error::InvalidPriority => write!(f, "Task priority must be an uppercase letter (A-Z)."),
+            Error::InvalidDate(date) => write!(
+                f,
+                "Invalid date '{}'. Dates must be YYYY-MM-DD, today, tomorrow, yesterday or +N[dw].",
+                date
+            ),
+            Error::InvalidFilter(explanation) => write!(f, "Invalid filter: {}", explanation),
+            Error::Io(explanation) => write!(f, "{}", explanation),
+            Error::InvalidConfig(explanation) => {
+                write!(f, "Invalid configuration file {}", explanation)
+            }
+            Error::NothingToUndo => write!(f, "There is nothing to undo."),
+            Error::NothingToRedo => write!(f, "There is nothing to redo."),
+            Error::ConcurrentModification(path) => write!(
+                f,
+                "{} was changed by another program while mama was running. Nothing was saved, please try again.",
+                path
+            ),
+            Error::Locked(path) => write!(
+                f,
+                "{} is being modified by another mama process. Please try again later.",
+                path
+            ),
         }
     }
 }
 
+impl std::error::Error for Error {}
+
 impl From<PriorityError> for Error {
     fn from(_e: PriorityError) -> Self {
         Self::InvalidPriority
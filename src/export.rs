@@ -0,0 +1,398 @@
+use crate::date::{format_date, local_midnight};
+use crate::error::Error;
+use crate::Task;
+use crate::TaskId;
+use gregorian::Date;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Formats that tasks can be exported to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    /// CSV with a header row, see RFC 4180
+    Csv,
+    /// A Markdown task list, as rendered by GitHub
+    Markdown,
+    /// An HTML table
+    Html,
+    /// iCalendar with a VTODO for each task, see RFC 5545
+    Ical,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "md" | "markdown" => Ok(ExportFormat::Markdown),
+            "html" => Ok(ExportFormat::Html),
+            "ical" | "ics" => Ok(ExportFormat::Ical),
+            _ => Err(Error::InvalidArgument(format!(
+                "unknown export format '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+/// Renders `tasks` as a document in `format`. Dates are formatted with
+/// `date_format`, except in CSV, which always has YYYY-MM-DD dates.
+pub fn render(format: ExportFormat, tasks: &[(Option<TaskId>, Task)], date_format: &str) -> String {
+    match format {
+        ExportFormat::Csv => csv(tasks),
+        ExportFormat::Markdown => markdown(tasks, date_format),
+        ExportFormat::Html => html(tasks, date_format),
+        ExportFormat::Ical => {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |x| x.as_secs() as i64);
+            ical(tasks, now)
+        }
+    }
+}
+
+/// The columns of a task in a table
+fn columns(id: Option<TaskId>, task: &Task, date: impl Fn(Option<Date>) -> String) -> [String; 6] {
+    [
+        id.map(|id| id.to_string()).unwrap_or_default(),
+        task.priority.map(|p| p.to_string()).unwrap_or_default(),
+        date(task.creation_date),
+        date(task.completion_date),
+        date(task.due_date()),
+        task.description().to_string(),
+    ]
+}
+
+const HEADINGS: [&str; 6] = ["ID", "Priority", "Created", "Completed", "Due", "Task"];
+
+/// CSV as in RFC 4180, with a header row
+fn csv(tasks: &[(Option<TaskId>, Task)]) -> String {
+    let field = |s: &str| match s.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", s.replace('"', "\"\"")),
+        false => s.to_string(),
+    };
+    let date = |date: Option<Date>| date.map(|d| d.to_string()).unwrap_or_default();
+
+    let mut csv = format!("Done,{}\r\n", HEADINGS.join(","));
+    for (id, task) in tasks {
+        let done = match task.completed {
+            true => "x",
+            false => "",
+        };
+        let row = columns(*id, task, date).map(|s| field(&s));
+        csv.push_str(&format!("{},{}\r\n", done, row.join(",")));
+    }
+    csv
+}
+
+/// A Markdown task list, e.g. `- [x] (A) Call Bob (ID 3, due 1.6.2021)`.
+/// A table can't be used, since checkboxes only render in list items.
+fn markdown(tasks: &[(Option<TaskId>, Task)], date_format: &str) -> String {
+    let escape = |s: &str| {
+        let mut escaped = String::new();
+        for c in s.chars() {
+            if "\\`*_[]<>".contains(c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    };
+
+    let mut markdown = String::new();
+    for (id, task) in tasks {
+        let checkbox = match task.completed {
+            true => "[x]",
+            false => "[ ]",
+        };
+        let priority = task.priority.map(|p| format!("({}) ", p));
+
+        let mut details = Vec::new();
+        if let Some(id) = id {
+            details.push(format!("ID {}", id));
+        }
+        for (name, date) in [
+            ("created", task.creation_date),
+            ("completed", task.completion_date),
+            ("due", task.due_date()),
+        ] {
+            if let Some(date) = date {
+                details.push(format!("{} {}", name, format_date(date, date_format)));
+            }
+        }
+        let details = match details.is_empty() {
+            true => String::new(),
+            false => format!(" ({})", details.join(", ")),
+        };
+
+        markdown.push_str(&format!(
+            "- {} {}{}{}\n",
+            checkbox,
+            priority.unwrap_or_default(),
+            escape(task.description()),
+            details
+        ));
+    }
+    markdown
+}
+
+/// An HTML table, to be pasted into a document
+fn html(tasks: &[(Option<TaskId>, Task)], date_format: &str) -> String {
+    let escape = |s: &str| {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    };
+    let date = |date: Option<Date>| date.map_or(String::new(), |d| format_date(d, date_format));
+
+    let mut html = String::from("<table>\n<thead>\n<tr><th>Done</th>");
+    for heading in HEADINGS {
+        html.push_str(&format!("<th>{}</th>", heading));
+    }
+    html.push_str("</tr>\n</thead>\n<tbody>\n");
+    for (id, task) in tasks {
+        let (class, checked) = match task.completed {
+            true => (" class=\"completed\"", " checked"),
+            false => ("", ""),
+        };
+        html.push_str(&format!(
+            "<tr{}><td><input type=\"checkbox\" disabled{}></td>",
+            class, checked
+        ));
+        for column in columns(*id, task, date) {
+            html.push_str(&format!("<td>{}</td>", escape(&column)));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>\n");
+    html
+}
+
+/// An iCalendar (RFC 5545) calendar with a VTODO for each task.
+/// `now` is the time of the export in seconds since the Unix epoch.
+///
+/// Priorities A to H become 1 to 8 and the rest 9, the lowest in
+/// iCalendar. Projects and contexts are CATEGORIES, written with their
+/// '+' or '@' so that they can be told apart when importing.
+fn ical(tasks: &[(Option<TaskId>, Task)], now: i64) -> String {
+    let timestamp = ical_timestamp(now);
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//mama//todo.txt//EN".to_string(),
+    ];
+    for (_, task) in tasks {
+        lines.push("BEGIN:VTODO".to_string());
+        lines.push(format!("UID:{}", uid(task)));
+        lines.push(format!("DTSTAMP:{}", timestamp));
+        lines.push(format!("SUMMARY:{}", escape_text(task.description())));
+        if let Some(priority) = task.priority {
+            let priority = (priority.0 as u32 - 'A' as u32 + 1).min(9);
+            lines.push(format!("PRIORITY:{}", priority));
+        }
+        if let Some(date) = task.creation_date {
+            lines.push(format!("CREATED:{}", ical_timestamp(local_midnight(date))));
+        }
+        if let Some(date) = task.threshold_date() {
+            lines.push(format!("DTSTART;VALUE=DATE:{}", ical_date(date)));
+        }
+        if let Some(date) = task.due_date() {
+            lines.push(format!("DUE;VALUE=DATE:{}", ical_date(date)));
+        }
+        if let Some(date) = task.completion_date {
+            lines.push(format!(
+                "COMPLETED:{}",
+                ical_timestamp(local_midnight(date))
+            ));
+        }
+        lines.push(match task.completed {
+            true => "STATUS:COMPLETED".to_string(),
+            false => "STATUS:NEEDS-ACTION".to_string(),
+        });
+        let categories = task
+            .projects()
+            .iter()
+            .map(|p| format!("+{}", p))
+            .chain(task.contexts().iter().map(|c| format!("@{}", c)))
+            .map(|category| escape_text(&category))
+            .collect::<Vec<String>>();
+        if !categories.is_empty() {
+            lines.push(format!("CATEGORIES:{}", categories.join(",")));
+        }
+        lines.push("END:VTODO".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+/// A UTC DATE-TIME value at `timestamp`, in seconds since the Unix epoch
+fn ical_timestamp(timestamp: i64) -> String {
+    let seconds = timestamp.rem_euclid(86400);
+    format!(
+        "{}T{:02}{:02}{:02}Z",
+        ical_date(Date::from_unix_timestamp(timestamp)),
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// Formats `date` as YYYYMMDD
+fn ical_date(date: Date) -> String {
+    format!(
+        "{:04}{:02}{:02}",
+        date.year().to_number(),
+        date.month().to_number(),
+        date.day()
+    )
+}
+
+/// A UID that stays the same over exports: the stable ID of the task if it
+/// has one, otherwise an FNV-1a hash of its creation date and description.
+/// Completing the task or changing its priority keeps the UID, but editing
+/// the description makes it a new one, unless the task has a stable ID.
+fn uid(task: &Task) -> String {
+    if let Some(id) = task.tag("id") {
+        return format!("{}@mama", id);
+    }
+
+    let created = task
+        .creation_date
+        .map(|d| d.to_string())
+        .unwrap_or_default();
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in format!("{} {}", created, task.description()).bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}@mama", hash)
+}
+
+/// Escapes a TEXT value, see RFC 5545 section 3.3.11
+fn escape_text(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Splits `line` into lines of at most 75 bytes, the continuation lines
+/// starting with a space, and terminates it with CRLF
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tasks() -> Vec<(Option<TaskId>, Task)> {
+        vec![
+            (
+                Some(TaskId(0)),
+                "(A) 2024-01-02 Call \"Bob\", <maybe> | Alice due:2024-01-05"
+                    .parse()
+                    .unwrap(),
+            ),
+            (None, "x 2024-01-03 2024-01-01 Pay rent".parse().unwrap()),
+        ]
+    }
+
+    #[test]
+    fn csv_fields_are_quoted() {
+        assert_eq!(
+            render(ExportFormat::Csv, &tasks(), "%d.%m.%Y"),
+            "Done,ID,Priority,Created,Completed,Due,Task\r\n\
+             ,1,A,2024-01-02,,2024-01-05,\"Call \"\"Bob\"\", <maybe> | Alice due:2024-01-05\"\r\n\
+             x,,,2024-01-01,2024-01-03,,Pay rent\r\n"
+        );
+    }
+
+    #[test]
+    fn markdown_has_checkboxes() {
+        assert_eq!(
+            render(ExportFormat::Markdown, &tasks(), "%d.%m.%Y"),
+            "- [ ] (A) Call \"Bob\", \\<maybe\\> | Alice due:2024-01-05 \
+             (ID 1, created 02.01.2024, due 05.01.2024)\n\
+             - [x] Pay rent (created 01.01.2024, completed 03.01.2024)\n"
+        );
+    }
+
+    #[test]
+    fn uids_are_stable() {
+        let uid = |line: &str| uid(&line.parse::<Task>().unwrap());
+        assert_eq!(uid("Call Bob id:k3x9"), "k3x9@mama");
+        assert_eq!(uid("Call Bob"), "7d34fbdfe573020c@mama");
+        assert_eq!(
+            uid("2024-01-02 Call Bob"),
+            uid("x 2024-01-05 2024-01-02 Call Bob")
+        );
+        assert_eq!(uid("Call Bob"), uid("(A) Call Bob"));
+        assert_ne!(uid("Call Bob"), uid("2024-01-02 Call Bob"));
+    }
+
+    #[test]
+    fn ical_todos() {
+        let ical = ical(&tasks(), 1704196800);
+        let lines = ical.split("\r\n").collect::<Vec<&str>>();
+        assert_eq!(
+            lines[..3],
+            [
+                "BEGIN:VCALENDAR",
+                "VERSION:2.0",
+                "PRODID:-//mama//todo.txt//EN"
+            ]
+        );
+        assert!(lines.contains(&"DTSTAMP:20240102T120000Z"));
+        assert!(lines.contains(&"SUMMARY:Call \"Bob\"\\, <maybe> | Alice due:2024-01-05"));
+        assert!(lines.contains(&"PRIORITY:1"));
+        // Dates are written as the UTC time of their local midnight
+        let midnight = |day: u8| ical_timestamp(local_midnight(Date::new(2024, 1, day).unwrap()));
+        assert!(lines.contains(&format!("CREATED:{}", midnight(2)).as_str()));
+        assert!(lines.contains(&"DUE;VALUE=DATE:20240105"));
+        assert!(lines.contains(&format!("COMPLETED:{}", midnight(3)).as_str()));
+        assert!(lines.contains(&"STATUS:COMPLETED"));
+        assert!(lines.contains(&"STATUS:NEEDS-ACTION"));
+        assert_eq!(
+            lines.iter().filter(|line| **line == "BEGIN:VTODO").count(),
+            2
+        );
+        assert_eq!(lines[lines.len() - 2..], ["END:VCALENDAR", ""]);
+    }
+
+    #[test]
+    fn long_lines_are_folded() {
+        let line = "x".repeat(80);
+        assert_eq!(
+            fold(&line),
+            format!("{}\r\n {}\r\n", &line[..75], &line[75..])
+        );
+        assert_eq!(fold("SUMMARY:ä"), "SUMMARY:ä\r\n");
+    }
+
+    #[test]
+    fn html_is_escaped() {
+        let html = render(ExportFormat::Html, &tasks(), "%Y-%m-%d");
+        assert!(
+            html.contains("<td>Call &quot;Bob&quot;, &lt;maybe&gt; | Alice due:2024-01-05</td>")
+        );
+        assert!(html.contains(
+            "<tr class=\"completed\"><td><input type=\"checkbox\" disabled checked></td>"
+        ));
+        assert!(html.starts_with("<table>") && html.ends_with("</table>\n"));
+    }
+}
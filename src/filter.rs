@@ -0,0 +1,362 @@
+use crate::date::parse_date;
+use crate::error::Error;
+use crate::Task;
+use crate::TaskPriority;
+use gregorian::Date;
+use regex_lite::Regex;
+
+/// Filter selects tasks with a small query language. Each argument is
+/// one term:
+///
+/// - `word` matches tasks whose description contains the word,
+///   ignoring case. An argument with spaces, or one in double quotes,
+///   is matched as a phrase.
+/// - `+project` and `@context` match tasks with exactly that project or
+///   context.
+/// - `pri:A` and `pri:A-C` match tasks with a priority in the range.
+/// - `due<today`, `created>=2024-01-01` etc. compare a date of the task,
+///   one of `due`, `created`, `completed` or `t`, using `<`, `<=`, `>`,
+///   `>=` or `=`. Tasks without the date never match.
+/// - `done` and `undone` match completed and unfinished tasks.
+/// - `/pattern/` matches descriptions against a regular expression.
+/// - `-term` matches tasks that the term doesn't match.
+///
+/// Terms must all match, unless separated by `OR`, which binds looser:
+/// `+home -done OR due<today` lists unfinished tasks of +home and all
+/// overdue ones. An empty filter matches every task.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Filter {
+    /// Alternatives of which at least one must match
+    any: Vec<Vec<Term>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Term {
+    Not(Box<Term>),
+    Text(String),
+    Project(String),
+    Context(String),
+    Priority(char, char),
+    Date(DateField, Comparison, Date),
+    Done(bool),
+    Regex(Pattern),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DateField {
+    Due,
+    Created,
+    Completed,
+    Threshold,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+/// Regex that can be compared by its source
+#[derive(Clone, Debug)]
+struct Pattern(Regex);
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Filter {
+    /// Parses a filter from the terms given on the command line, e.g.
+    /// `["+work", "-done", "OR", "due<today"]`
+    pub fn parse(args: &[String]) -> Result<Self, Error> {
+        let mut any = vec![Vec::new()];
+        for arg in args {
+            if arg == "OR" {
+                if any.last().is_some_and(|all| all.is_empty()) {
+                    return Err(Error::InvalidFilter(
+                        "OR needs a term on both sides".to_string(),
+                    ));
+                }
+                any.push(Vec::new());
+            } else if let Some(all) = any.last_mut() {
+                all.push(Term::parse(arg)?);
+            }
+        }
+        if any.len() > 1 && any.last().is_some_and(|all| all.is_empty()) {
+            return Err(Error::InvalidFilter(
+                "OR needs a term on both sides".to_string(),
+            ));
+        }
+
+        any.retain(|all| !all.is_empty());
+        Ok(Filter { any })
+    }
+
+    /// Returns a filter matching the tasks that both `self` and `other`
+    /// match
+    pub fn and(self, other: Filter) -> Filter {
+        if self.is_empty() {
+            return other;
+        }
+        if other.is_empty() {
+            return self;
+        }
+        let any = self
+            .any
+            .iter()
+            .flat_map(|a| {
+                other
+                    .any
+                    .iter()
+                    .map(move |b| [a.clone(), b.clone()].concat())
+            })
+            .collect();
+        Filter { any }
+    }
+
+    /// Returns true if the filter has no terms
+    pub fn is_empty(&self) -> bool {
+        self.any.is_empty()
+    }
+
+    /// Returns true if `task` matches the filter. An empty filter matches
+    /// all tasks.
+    pub fn matches(&self, task: &Task) -> bool {
+        self.is_empty()
+            || self
+                .any
+                .iter()
+                .any(|all| all.iter().all(|term| term.matches(task)))
+    }
+}
+
+impl Term {
+    fn parse(arg: &str) -> Result<Self, Error> {
+        if let Some(term) = arg.strip_prefix('-').filter(|t| !t.is_empty()) {
+            return Ok(Term::Not(Box::new(Term::parse(term)?)));
+        }
+        if let Some(phrase) = arg
+            .strip_prefix('"')
+            .and_then(|s| s.strip_suffix('"'))
+            .filter(|s| !s.is_empty())
+        {
+            return Ok(Term::Text(phrase.to_lowercase()));
+        }
+        if arg.contains(char::is_whitespace) {
+            return Ok(Term::Text(arg.to_lowercase()));
+        }
+        if let Some(pattern) = arg
+            .strip_prefix('/')
+            .and_then(|s| s.strip_suffix('/'))
+            .filter(|s| !s.is_empty())
+        {
+            return Regex::new(pattern)
+                .map(|regex| Term::Regex(Pattern(regex)))
+                .map_err(|_| {
+                    Error::InvalidFilter(format!("invalid regular expression '{}'", arg))
+                });
+        }
+        if let Some(project) = arg.strip_prefix('+').filter(|p| !p.is_empty()) {
+            return Ok(Term::Project(project.to_string()));
+        }
+        if let Some(context) = arg.strip_prefix('@').filter(|c| !c.is_empty()) {
+            return Ok(Term::Context(context.to_string()));
+        }
+        if let Some(range) = arg.strip_prefix("pri:") {
+            return parse_priority_range(range);
+        }
+        match arg {
+            "done" => return Ok(Term::Done(true)),
+            "undone" => return Ok(Term::Done(false)),
+            _ => (),
+        }
+        if let Some(term) = parse_date_comparison(arg)? {
+            return Ok(term);
+        }
+        Ok(Term::Text(arg.to_lowercase()))
+    }
+
+    fn matches(&self, task: &Task) -> bool {
+        match self {
+            Term::Not(term) => !term.matches(task),
+            Term::Text(text) => task.description().to_lowercase().contains(text),
+            Term::Project(project) => task.has_project(project),
+            Term::Context(context) => task.has_context(context),
+            Term::Priority(highest, lowest) => task
+                .priority
+                .is_some_and(|p| (*highest..=*lowest).contains(&p.0)),
+            Term::Date(field, comparison, date) => {
+                let value = match field {
+                    DateField::Due => task.due_date(),
+                    DateField::Created => task.creation_date,
+                    DateField::Completed => task.completion_date,
+                    DateField::Threshold => task.threshold_date(),
+                };
+                value.is_some_and(|value| comparison.holds(value, *date))
+            }
+            Term::Done(completed) => task.completed == *completed,
+            Term::Regex(Pattern(regex)) => regex.is_match(task.description()),
+        }
+    }
+}
+
+impl Comparison {
+    fn holds(self, a: Date, b: Date) -> bool {
+        match self {
+            Comparison::Less => a < b,
+            Comparison::LessOrEqual => a <= b,
+            Comparison::Equal => a == b,
+            Comparison::GreaterOrEqual => a >= b,
+            Comparison::Greater => a > b,
+        }
+    }
+}
+
+/// Parses the `A` or `A-C` of `pri:A-C`
+fn parse_priority_range(range: &str) -> Result<Term, Error> {
+    let (highest, lowest) = range.split_once('-').unwrap_or((range, range));
+    let (highest, lowest) = (
+        TaskPriority::from_letter(highest)?.0,
+        TaskPriority::from_letter(lowest)?.0,
+    );
+    match highest <= lowest {
+        true => Ok(Term::Priority(highest, lowest)),
+        false => Ok(Term::Priority(lowest, highest)),
+    }
+}
+
+/// Parses terms such as `due<today`. Returns None if `arg` doesn't start
+/// with a date field followed by a comparison.
+fn parse_date_comparison(arg: &str) -> Result<Option<Term>, Error> {
+    let fields = [
+        ("due", DateField::Due),
+        ("created", DateField::Created),
+        ("completed", DateField::Completed),
+        ("t", DateField::Threshold),
+    ];
+    // Two-character operators must be tried first
+    let comparisons = [
+        ("<=", Comparison::LessOrEqual),
+        (">=", Comparison::GreaterOrEqual),
+        ("<", Comparison::Less),
+        (">", Comparison::Greater),
+        ("=", Comparison::Equal),
+    ];
+
+    for (name, field) in fields {
+        let Some(rest) = arg.strip_prefix(name) else {
+            continue;
+        };
+        for (operator, comparison) in comparisons {
+            if let Some(date) = rest.strip_prefix(operator) {
+                return Ok(Some(Term::Date(field, comparison, parse_date(date)?)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(args: &[&str]) -> Filter {
+        let args = args.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        Filter::parse(&args).unwrap()
+    }
+
+    fn task(line: &str) -> Task {
+        line.parse().unwrap()
+    }
+
+    #[test]
+    fn words_must_all_match() {
+        let task = task("Fix the roof +house @home");
+        assert!(filter(&[]).matches(&task));
+        assert!(filter(&["ROOF", "fix"]).matches(&task));
+        assert!(!filter(&["roof", "window"]).matches(&task));
+        assert!(filter(&["+house", "@home"]).matches(&task));
+        assert!(!filter(&["+hous"]).matches(&task));
+        assert!(filter(&["hous"]).matches(&task));
+    }
+
+    #[test]
+    fn negation() {
+        let task = task("Fix the roof +house");
+        assert!(filter(&["-window"]).matches(&task));
+        assert!(!filter(&["-roof"]).matches(&task));
+        assert!(!filter(&["-+house"]).matches(&task));
+        assert!(filter(&["-done"]).matches(&task));
+    }
+
+    #[test]
+    fn or_binds_looser_than_and() {
+        let f = filter(&["roof", "+house", "OR", "@office"]);
+        assert!(f.matches(&task("Fix the roof +house")));
+        assert!(f.matches(&task("Call Bob @office")));
+        assert!(!f.matches(&task("Fix the roof")));
+        assert!(Filter::parse(&["OR".to_string()]).is_err());
+        assert!(Filter::parse(&["roof".to_string(), "OR".to_string()]).is_err());
+    }
+
+    #[test]
+    fn phrases() {
+        let task = task("Fix the roof");
+        assert!(filter(&["the roof"]).matches(&task));
+        assert!(filter(&["\"Fix the\""]).matches(&task));
+        assert!(!filter(&["roof the"]).matches(&task));
+        assert!(!filter(&["-the roof"]).matches(&task));
+    }
+
+    #[test]
+    fn priority_ranges() {
+        assert!(filter(&["pri:A-C"]).matches(&task("(B) Pay rent")));
+        assert!(filter(&["pri:C-A"]).matches(&task("(B) Pay rent")));
+        assert!(!filter(&["pri:A"]).matches(&task("(B) Pay rent")));
+        assert!(!filter(&["pri:A-Z"]).matches(&task("Pay rent")));
+        assert!(Filter::parse(&["pri:1".to_string()]).is_err());
+    }
+
+    #[test]
+    fn date_comparisons() {
+        let task = task("2024-01-01 Pay rent due:2024-02-01");
+        assert!(filter(&["due<today"]).matches(&task));
+        assert!(filter(&["due=2024-02-01"]).matches(&task));
+        assert!(filter(&["created>=2024-01-01"]).matches(&task));
+        assert!(!filter(&["created>2024-01-01"]).matches(&task));
+        assert!(!filter(&["completed<today"]).matches(&task));
+        assert!(filter(&["-completed<today"]).matches(&task));
+        assert!(Filter::parse(&["due<someday".to_string()]).is_err());
+        // Not a comparison, just a word
+        assert!(filter(&["duet"]) == filter(&["DUET"]));
+    }
+
+    #[test]
+    fn completion() {
+        assert!(filter(&["done"]).matches(&task("x Pay rent")));
+        assert!(!filter(&["undone"]).matches(&task("x Pay rent")));
+        assert!(filter(&["undone"]).matches(&task("Pay rent")));
+    }
+
+    #[test]
+    fn combined_filters() {
+        let both = filter(&["+home", "OR", "+work"]).and(filter(&["-done"]));
+        assert!(both.matches(&task("Fix the roof +home")));
+        assert!(both.matches(&task("Call Bob +work")));
+        assert!(!both.matches(&task("x Pay rent +home")));
+        assert!(!both.matches(&task("Water plants")));
+        assert_eq!(filter(&[]).and(filter(&["done"])), filter(&["done"]));
+    }
+
+    #[test]
+    fn regular_expressions() {
+        let task = task("Call Bob at 555-1234");
+        assert!(filter(&["/\\d{3}-\\d{4}/"]).matches(&task));
+        assert!(!filter(&["/^Bob/"]).matches(&task));
+        assert!(Filter::parse(&["/(/".to_string()]).is_err());
+    }
+}
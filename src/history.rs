@@ -0,0 +1,286 @@
+use gregorian::Date;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A saved change to todo.txt
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    /// Number of the change, increasing with each change
+    pub id: usize,
+    /// Seconds since the Unix epoch
+    pub timestamp: i64,
+    /// The command line that made the change
+    pub command: String,
+}
+
+impl Entry {
+    /// Returns the time of the change as "YYYY-MM-DD HH:MM" in UTC
+    pub fn time(&self) -> String {
+        let seconds = self.timestamp.rem_euclid(86400);
+        format!(
+            "{} {:02}:{:02}",
+            Date::from_unix_timestamp(self.timestamp),
+            seconds / 3600,
+            seconds % 3600 / 60
+        )
+    }
+}
+
+/// History is the journal of the latest saves of a todo.txt, used for
+/// undoing and redoing changes.
+///
+/// The journal is kept in a directory of its own. For every entry, it holds
+/// the contents of todo.txt before and after the change, and the lines the
+/// change appended to done.txt, if any. Entries past the
+/// cursor have been undone and can be redone, until a new change is
+/// recorded.
+pub struct History {
+    dir: PathBuf,
+    entries: Vec<Entry>,
+    /// Number of entries that are in effect
+    cursor: usize,
+}
+
+impl History {
+    /// Reads the journal in `dir`. A missing journal has no entries.
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        let mut history = Self {
+            dir: dir.to_path_buf(),
+            entries: Vec::new(),
+            cursor: 0,
+        };
+
+        let contents = match std::fs::read_to_string(history.journal_path()) {
+            Ok(contents) => contents,
+            Err(x) if x.kind() == io::ErrorKind::NotFound => return Ok(history),
+            Err(x) => return Err(x),
+        };
+
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "corrupted undo history");
+        let mut lines = contents.lines();
+        history.cursor = lines
+            .next()
+            .and_then(|line| line.strip_prefix("cursor "))
+            .and_then(|n| n.parse().ok())
+            .ok_or_else(invalid)?;
+        for line in lines {
+            let mut fields = line.splitn(3, '\t');
+            let mut field = || fields.next().ok_or_else(invalid);
+            history.entries.push(Entry {
+                id: field()?.parse().map_err(|_| invalid())?,
+                timestamp: field()?.parse().map_err(|_| invalid())?,
+                command: field()?.to_string(),
+            });
+        }
+        history.cursor = history.cursor.min(history.entries.len());
+
+        Ok(history)
+    }
+
+    /// Writes the journal to the disk
+    pub fn save(&self) -> io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let mut contents = format!("cursor {}\n", self.cursor);
+        for entry in &self.entries {
+            contents.push_str(&format!(
+                "{}\t{}\t{}\n",
+                entry.id, entry.timestamp, entry.command
+            ));
+        }
+        std::fs::write(self.journal_path(), contents)
+    }
+
+    /// Returns the entries from oldest to newest
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Returns the number of entries that have not been undone
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Records a change made by `command`, which turned the contents of
+    /// todo.txt from `before` to `after` and appended the lines `archived`
+    /// to done.txt. Entries that have been undone can no longer be redone,
+    /// and at most `retention` entries are kept.
+    pub fn record(
+        &mut self,
+        command: &str,
+        before: &str,
+        after: &str,
+        archived: &[String],
+        retention: usize,
+    ) -> io::Result<()> {
+        let undone = self.entries.split_off(self.cursor);
+        self.remove_snapshots(&undone);
+
+        let entry = Entry {
+            id: self.entries.last().map_or(1, |entry| entry.id + 1),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |x| x.as_secs() as i64),
+            command: command.replace(['\t', '\n'], " "),
+        };
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.before_path(&entry), before)?;
+        std::fs::write(self.after_path(&entry), after)?;
+        if !archived.is_empty() {
+            let lines = archived.iter().map(|line| format!("{}\n", line));
+            std::fs::write(self.archived_path(&entry), lines.collect::<String>())?;
+        }
+        self.entries.push(entry);
+
+        let excess = self.entries.len().saturating_sub(retention);
+        let expired = self.entries.drain(..excess).collect::<Vec<Entry>>();
+        self.remove_snapshots(&expired);
+        self.cursor = self.entries.len();
+
+        self.save()
+    }
+
+    /// Moves the cursor back by one entry and returns the entry,
+    /// or None if there is nothing to undo
+    pub fn undo(&mut self) -> Option<Entry> {
+        self.cursor = self.cursor.checked_sub(1)?;
+        Some(self.entries[self.cursor].clone())
+    }
+
+    /// Moves the cursor forward by one entry and returns the entry,
+    /// or None if there is nothing to redo
+    pub fn redo(&mut self) -> Option<Entry> {
+        let entry = self.entries.get(self.cursor)?.clone();
+        self.cursor += 1;
+        Some(entry)
+    }
+
+    /// Location of the contents of todo.txt before `entry` was made
+    pub fn before_path(&self, entry: &Entry) -> PathBuf {
+        self.dir.join(format!("{}.before", entry.id))
+    }
+
+    /// Location of the contents of todo.txt after `entry` was made
+    pub fn after_path(&self, entry: &Entry) -> PathBuf {
+        self.dir.join(format!("{}.after", entry.id))
+    }
+
+    /// Returns the lines that `entry` appended to done.txt
+    pub fn archived(&self, entry: &Entry) -> io::Result<Vec<String>> {
+        match std::fs::read_to_string(self.archived_path(entry)) {
+            Ok(contents) => Ok(contents.lines().map(String::from).collect()),
+            Err(x) if x.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(x) => Err(x),
+        }
+    }
+
+    fn archived_path(&self, entry: &Entry) -> PathBuf {
+        self.dir.join(format!("{}.archived", entry.id))
+    }
+
+    fn journal_path(&self) -> PathBuf {
+        self.dir.join("journal")
+    }
+
+    fn remove_snapshots(&self, entries: &[Entry]) {
+        for entry in entries {
+            // A missing snapshot is as good as a removed one
+            let _ = std::fs::remove_file(self.before_path(entry));
+            let _ = std::fs::remove_file(self.after_path(entry));
+            let _ = std::fs::remove_file(self.archived_path(entry));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(name: &str) -> History {
+        let dir = std::env::temp_dir().join(format!("mama-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        History::open(&dir).unwrap()
+    }
+
+    fn commands(history: &History) -> Vec<&str> {
+        history
+            .entries()
+            .iter()
+            .map(|entry| entry.command.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn undo_and_redo_move_the_cursor() {
+        let mut history = history("cursor");
+        history.record("add a", "", "a\n", &[], 10).unwrap();
+        history.record("add b", "a\n", "a\nb\n", &[], 10).unwrap();
+
+        let entry = history.undo().unwrap();
+        assert_eq!(entry.command, "add b");
+        let before = std::fs::read_to_string(history.before_path(&entry)).unwrap();
+        assert_eq!(before, "a\n");
+        assert_eq!(history.undo().unwrap().command, "add a");
+        assert_eq!(history.undo(), None);
+        assert_eq!(history.cursor(), 0);
+
+        assert_eq!(history.redo().unwrap().command, "add a");
+        assert_eq!(history.cursor(), 1);
+        history.save().unwrap();
+
+        let reopened = History::open(&history.dir).unwrap();
+        assert_eq!(reopened.cursor(), 1);
+        assert_eq!(reopened.entries(), history.entries());
+    }
+
+    #[test]
+    fn recording_discards_undone_entries() {
+        let mut history = history("discard");
+        history.record("add a", "", "a\n", &[], 10).unwrap();
+        history.record("add b", "a\n", "a\nb\n", &[], 10).unwrap();
+        history.undo().unwrap();
+        history.record("add c", "a\n", "a\nc\n", &[], 10).unwrap();
+
+        assert_eq!(commands(&history), ["add a", "add c"]);
+        assert_eq!(history.redo(), None);
+        let newest = &history.entries()[1];
+        let after = std::fs::read_to_string(history.after_path(newest)).unwrap();
+        assert_eq!(after, "a\nc\n");
+    }
+
+    #[test]
+    fn archived_lines_are_kept() {
+        let mut history = history("archived");
+        history.record("add a", "", "a\n", &[], 10).unwrap();
+        let archived = ["x 2024-01-02 a".to_string()];
+        history
+            .record("archive", "x 2024-01-02 a\n", "", &archived, 10)
+            .unwrap();
+
+        let entries = history.entries().to_vec();
+        assert_eq!(history.archived(&entries[0]).unwrap(), Vec::<String>::new());
+        assert_eq!(history.archived(&entries[1]).unwrap(), archived);
+    }
+
+    #[test]
+    fn old_entries_expire() {
+        let mut history = history("expire");
+        for n in 0..5 {
+            history
+                .record(&format!("add {}", n), "", "", &[], 3)
+                .unwrap();
+        }
+        assert_eq!(commands(&history), ["add 2", "add 3", "add 4"]);
+        assert_eq!(history.cursor(), 3);
+    }
+
+    #[test]
+    fn time_is_formatted_in_utc() {
+        let entry = Entry {
+            id: 1,
+            timestamp: 1623243900,
+            command: String::new(),
+        };
+        assert_eq!(entry.time(), "2021-06-09 13:05");
+    }
+}
@@ -0,0 +1,593 @@
+use crate::date::local_date;
+use crate::error::Error;
+use crate::Task;
+use crate::TaskPriority;
+use gregorian::Date;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Formats that tasks can be imported from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImportFormat {
+    /// The VTODOs of an iCalendar file
+    Ical,
+    /// The output of `task export`
+    TaskwarriorJson,
+    /// CSV with a header row naming the columns
+    Csv,
+    /// One task per line
+    Lines,
+}
+
+impl std::str::FromStr for ImportFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ical" | "ics" => Ok(ImportFormat::Ical),
+            "taskwarrior-json" | "taskwarrior" => Ok(ImportFormat::TaskwarriorJson),
+            "csv" => Ok(ImportFormat::Csv),
+            "lines" => Ok(ImportFormat::Lines),
+            _ => Err(Error::InvalidArgument(format!(
+                "unknown import format '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+impl ImportFormat {
+    /// Guesses the format of a file from its extension
+    pub fn of(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "json" => Some(ImportFormat::TaskwarriorJson),
+            "txt" | "md" => Some(ImportFormat::Lines),
+            extension => extension.parse().ok(),
+        }
+    }
+}
+
+/// Converts the contents of a file in `format` into tasks
+pub fn parse(format: ImportFormat, contents: &str) -> Result<Vec<Task>, Error> {
+    match format {
+        ImportFormat::Ical => ical(contents),
+        ImportFormat::TaskwarriorJson => taskwarrior(contents),
+        ImportFormat::Csv => csv(contents),
+        ImportFormat::Lines => Ok(lines(contents)),
+    }
+}
+
+/// Separates the tasks that are already in `existing` from `tasks`, and
+/// returns the new tasks and the duplicates. Tasks with the same
+/// description, ignoring case and spacing, are duplicates, so the same
+/// file can be imported twice without adding anything the second time.
+pub fn remove_duplicates<'a>(
+    existing: impl Iterator<Item = &'a Task>,
+    tasks: Vec<Task>,
+) -> (Vec<Task>, Vec<Task>) {
+    let key = |task: &Task| {
+        task.description()
+            .to_lowercase()
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ")
+    };
+    let mut seen = existing.map(key).collect::<HashSet<String>>();
+    tasks.into_iter().partition(|task| seen.insert(key(task)))
+}
+
+/// Marks `task` as completed on `date`, or today if the date is unknown
+fn complete(task: &mut Task, date: Option<Date>) {
+    task.complete();
+    task.completion_date = date.or(task.completion_date);
+}
+
+/// Makes a project or context out of a name that may contain spaces
+fn word(name: &str) -> String {
+    name.trim().replace(char::is_whitespace, "_")
+}
+
+/// Converts the VTODOs of an iCalendar file into tasks, the inverse of
+/// the iCalendar export. Other components, such as events, are skipped.
+fn ical(contents: &str) -> Result<Vec<Task>, Error> {
+    // Continuation lines start with a space or a tab
+    let unfolded = contents
+        .replace("\r\n", "\n")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+
+    let mut tasks = Vec::new();
+    let mut todo: Option<Vec<(String, String)>> = None;
+    for line in unfolded.lines() {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        // Parameters such as ;VALUE=DATE are not needed
+        let name = name.split(';').next().unwrap_or(name).to_uppercase();
+        match (name.as_str(), value, &mut todo) {
+            ("BEGIN", "VTODO", _) => todo = Some(Vec::new()),
+            ("END", "VTODO", Some(properties)) => {
+                tasks.push(vtodo(properties)?);
+                todo = None;
+            }
+            (_, _, Some(properties)) => properties.push((name, value.to_string())),
+            _ => (),
+        }
+    }
+    Ok(tasks)
+}
+
+fn vtodo(properties: &[(String, String)]) -> Result<Task, Error> {
+    let property = |name: &str| {
+        properties
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    };
+    let date = |name: &str| match property(name) {
+        Some(value) => ical_date(value).map(Some),
+        None => Ok(None),
+    };
+
+    let summary = unescape_text(property("SUMMARY").unwrap_or_default());
+    let mut task = Task::new(&summary);
+    if task.description().is_empty() {
+        return Err(Error::InvalidArgument("a VTODO has no SUMMARY".to_string()));
+    }
+
+    task.priority = match property("PRIORITY").map(str::trim) {
+        None | Some("0") => None,
+        Some(n) => match n.parse::<u8>() {
+            Ok(n @ 1..=9) => Some(TaskPriority((b'A' + n - 1) as char)),
+            _ => return Err(Error::InvalidArgument(format!("invalid PRIORITY '{}'", n))),
+        },
+    };
+    task.creation_date = date("CREATED")?;
+    if task.threshold_date().is_none() {
+        task.set_threshold_date(date("DTSTART")?);
+    }
+    if task.due_date().is_none() {
+        task.set_due_date(date("DUE")?);
+    }
+    for category in property("CATEGORIES").map(split_list).unwrap_or_default() {
+        match (category.strip_prefix('+'), category.strip_prefix('@')) {
+            (Some(project), _) => task.add_project(project),
+            (_, Some(context)) => task.add_context(context),
+            _ => task.add_project(&word(&category)),
+        }
+    }
+
+    let completion_date = date("COMPLETED")?;
+    if property("STATUS") == Some("COMPLETED") || completion_date.is_some() {
+        complete(&mut task, completion_date);
+    }
+    Ok(task)
+}
+
+/// Converts the output of `task export` into tasks. Projects become
+/// +projects, tags @contexts and the priorities H, M and L become A, B
+/// and C. Deleted tasks and the templates of recurring tasks are skipped.
+fn taskwarrior(contents: &str) -> Result<Vec<Task>, Error> {
+    let invalid = |e: serde_json::Error| Error::InvalidArgument(format!("invalid JSON: {}", e));
+    // Older versions of Taskwarrior export one object per line
+    let entries = match contents.trim_start().starts_with('[') {
+        true => serde_json::from_str::<Vec<Value>>(contents).map_err(invalid)?,
+        false => contents
+            .lines()
+            .map(|line| line.trim().trim_end_matches(','))
+            .filter(|line| !line.is_empty())
+            .map(serde_json::from_str::<Value>)
+            .collect::<Result<_, _>>()
+            .map_err(invalid)?,
+    };
+
+    let mut tasks = Vec::new();
+    for entry in entries {
+        let field = |name: &str| entry.get(name).and_then(Value::as_str);
+        let date = |name: &str| match field(name) {
+            Some(value) => ical_date(value).map(Some),
+            None => Ok(None),
+        };
+        let status = field("status").unwrap_or("pending");
+        if status == "deleted" || status == "recurring" {
+            continue;
+        }
+
+        let description = field("description").unwrap_or_default();
+        let mut task = Task::new(&description.replace(['\r', '\n'], " "));
+        if task.description().is_empty() {
+            return Err(Error::InvalidArgument(
+                "a Taskwarrior task has no description".to_string(),
+            ));
+        }
+        task.priority = match field("priority") {
+            Some("H") => Some(TaskPriority('A')),
+            Some("M") => Some(TaskPriority('B')),
+            Some("L") => Some(TaskPriority('C')),
+            _ => None,
+        };
+        task.creation_date = date("entry")?;
+        task.set_due_date(date("due")?);
+        task.set_threshold_date(date("scheduled")?.or(date("wait")?));
+        if let Some(project) = field("project") {
+            task.add_project(&word(project));
+        }
+        let tags = entry.get("tags").and_then(Value::as_array);
+        for tag in tags.into_iter().flatten().filter_map(Value::as_str) {
+            task.add_context(&word(tag));
+        }
+        if status == "completed" {
+            complete(&mut task, date("end")?);
+        }
+        tasks.push(task);
+    }
+    Ok(tasks)
+}
+
+/// Converts a CSV file with a header row into tasks. The columns are
+/// recognized by their headings, ignoring case: the description is in
+/// "Task", "Description", "Title" or "Summary", and optionally "Done",
+/// "Priority", "Created", "Completed", "Due", "Project" and "Context"
+/// columns give the rest. This reads the CSV export of mama, too.
+fn csv(contents: &str) -> Result<Vec<Task>, Error> {
+    let mut records = csv_records(contents).into_iter();
+    let headings = records
+        .next()
+        .unwrap_or_default()
+        .iter()
+        .map(|heading| heading.trim().to_lowercase())
+        .collect::<Vec<String>>();
+    let column = |names: &[&str]| headings.iter().position(|h| names.contains(&h.as_str()));
+    let description = column(&["task", "description", "title", "summary"]).ok_or_else(|| {
+        Error::InvalidArgument("the CSV file has no Task or Description column".to_string())
+    })?;
+    let done = column(&["done", "status"]);
+    let priority = column(&["priority"]);
+    let created = column(&["created"]);
+    let completed = column(&["completed"]);
+    let due = column(&["due"]);
+    let project = column(&["project"]);
+    let context = column(&["context"]);
+
+    let mut tasks = Vec::new();
+    for record in records {
+        let field = |column: Option<usize>| {
+            column
+                .and_then(|column| record.get(column))
+                .map(|s| s.trim())
+                .filter(|s| !s.is_empty())
+        };
+        let date = |column: Option<usize>| match field(column) {
+            Some(value) => value
+                .parse::<Date>()
+                .map(Some)
+                .map_err(|_| Error::InvalidDate(value.to_string())),
+            None => Ok(None),
+        };
+
+        let Some(description) = field(Some(description)) else {
+            continue;
+        };
+        // A task is a single line
+        let mut task = Task::new(&description.replace(['\r', '\n'], " "));
+        task.priority = field(priority).map(TaskPriority::from_letter).transpose()?;
+        task.creation_date = date(created)?;
+        if task.due_date().is_none() {
+            task.set_due_date(date(due)?);
+        }
+        if let Some(project) = field(project) {
+            task.add_project(&word(project));
+        }
+        if let Some(context) = field(context) {
+            task.add_context(&word(context));
+        }
+
+        let completion_date = date(completed)?;
+        let done = field(done).map(|s| s.to_lowercase());
+        if matches!(
+            done.as_deref(),
+            Some("x" | "yes" | "true" | "1" | "done" | "completed")
+        ) || completion_date.is_some()
+        {
+            complete(&mut task, completion_date);
+        }
+        tasks.push(task);
+    }
+    Ok(tasks)
+}
+
+/// Splits CSV as in RFC 4180 into records of fields
+fn csv_records(contents: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\r', false) => (),
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            (c, _) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+/// Converts a plain list into tasks, one for each line. A line may be in
+/// the todo.txt format, and bullets and checkboxes of Markdown lists are
+/// removed, a checked box marking the task as completed. If any line is a
+/// list item, the input is taken as Markdown and its headings are skipped.
+fn lines(contents: &str) -> Vec<Task> {
+    fn bullet(line: &str) -> Option<&str> {
+        ["- ", "* ", "+ "]
+            .iter()
+            .find_map(|bullet| line.strip_prefix(bullet))
+    }
+    let markdown = contents.lines().any(|line| bullet(line.trim()).is_some());
+
+    let mut tasks = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        let heading = line.starts_with('#') && line.trim_start_matches('#').starts_with(' ');
+        if markdown && heading {
+            continue;
+        }
+        let line = bullet(line).unwrap_or(line).trim_start();
+        let (line, checked) = match line.get(..3) {
+            Some("[ ]") => (&line[3..], false),
+            Some("[x]" | "[X]") => (&line[3..], true),
+            _ => (line, false),
+        };
+        if let Ok(mut task) = line.trim().parse::<Task>() {
+            if checked && !task.completed {
+                complete(&mut task, None);
+            }
+            tasks.push(task);
+        }
+    }
+    tasks
+}
+
+/// Parses the date of a DATE or DATE-TIME value, e.g. 20240105 or
+/// 20240105T120000Z. A time in UTC, ending in Z, is converted to the
+/// local date, while other times are already local.
+fn ical_date(value: &str) -> Result<Date, Error> {
+    let invalid = || Error::InvalidDate(value.to_string());
+    let digits = value.get(..8).ok_or_else(invalid)?;
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let number = |range: std::ops::Range<usize>| digits[range].parse::<u8>();
+    let date = Date::new(
+        digits[..4].parse::<i16>().map_err(|_| invalid())?,
+        number(4..6).map_err(|_| invalid())?,
+        number(6..8).map_err(|_| invalid())?,
+    )
+    .map_err(|_| invalid())?;
+
+    let time = match value[8..]
+        .strip_prefix('T')
+        .and_then(|t| t.strip_suffix('Z'))
+    {
+        Some(time) if time.len() == 6 && time.bytes().all(|b| b.is_ascii_digit()) => time,
+        _ => return Ok(date),
+    };
+    let number = |range: std::ops::Range<usize>| time[range].parse::<i64>().unwrap_or(0);
+    let seconds = number(0..2) * 3600 + number(2..4) * 60 + number(4..6);
+    Ok(local_date(date.to_unix_timestamp() + seconds))
+}
+
+/// Splits a list of TEXT values at unescaped commas
+fn split_list(value: &str) -> Vec<String> {
+    let mut values = vec![String::new()];
+    let mut escaped = false;
+    for c in value.chars() {
+        if c == ',' && !escaped {
+            values.push(String::new());
+        } else if let Some(last) = values.last_mut() {
+            last.push(c);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    values
+        .iter()
+        .map(|value| unescape_text(value))
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+/// Reverses the escaping of a TEXT value. Line breaks become spaces, since
+/// a task is a single line.
+fn unescape_text(value: &str) -> String {
+    let mut text = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match (c, c == '\\') {
+            (_, true) => match chars.next() {
+                Some('n' | 'N') => text.push(' '),
+                Some(c) => text.push(c),
+                None => (),
+            },
+            (c, false) => text.push(c),
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{render, ExportFormat};
+
+    #[test]
+    fn ical_round_trip() {
+        let tasks = [
+            "(B) 2024-01-02 Call Bob, the plumber +house @phone due:2024-01-05 t:2024-01-03",
+            "x 2024-01-04 2024-01-01 Pay rent; all of it pri:A",
+            "Backslashes \\ are kept",
+        ]
+        .map(|line| (None, line.parse::<Task>().unwrap()));
+
+        let ics = render(ExportFormat::Ical, &tasks, "%Y-%m-%d");
+        let imported = ical(&ics).unwrap();
+        assert_eq!(imported, tasks.map(|(_, task)| task));
+    }
+
+    #[test]
+    fn vtodos_from_other_clients() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+                   BEGIN:VEVENT\r\n\
+                   SUMMARY:Not a task\r\n\
+                   END:VEVENT\r\n\
+                   BEGIN:VTODO\r\n\
+                   SUMMARY:Renew the passport before the su\r\n mmer holiday\r\n\
+                   PRIORITY:9\r\n\
+                   DUE;TZID=Europe/Helsinki:20240601T120000\r\n\
+                   CATEGORIES:Travel,Errands\r\n\
+                   STATUS:NEEDS-ACTION\r\n\
+                   END:VTODO\r\n\
+                   END:VCALENDAR\r\n";
+        let tasks = ical(ics).unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(
+            tasks[0].to_string(),
+            "(I) Renew the passport before the summer holiday due:2024-06-01 +Travel +Errands"
+        );
+    }
+
+    #[test]
+    fn invalid_vtodos() {
+        let vtodo =
+            |property: &str| format!("BEGIN:VTODO\nSUMMARY:Pay rent\n{}\nEND:VTODO\n", property);
+        assert!(ical(&vtodo("DUE:2024-06-01")).is_err());
+        assert!(ical(&vtodo("PRIORITY:10")).is_err());
+        assert!(ical("BEGIN:VTODO\nEND:VTODO\n").is_err());
+    }
+
+    #[test]
+    fn format_is_guessed_from_extension() {
+        assert_eq!(
+            ImportFormat::of(Path::new("calendar.ICS")),
+            Some(ImportFormat::Ical)
+        );
+        assert_eq!(
+            ImportFormat::of(Path::new("todo.txt")),
+            Some(ImportFormat::Lines)
+        );
+        assert_eq!(ImportFormat::of(Path::new("notes.doc")), None);
+    }
+
+    #[test]
+    fn taskwarrior_export() {
+        let json = r#"[
+            {"id":1,"description":"Fix the roof","entry":"20240102T101500Z","due":"20240110T230000Z",
+             "priority":"H","project":"House repairs","tags":["home","weekend"],"status":"pending"},
+            {"id":0,"description":"Pay rent","entry":"20240101T000000Z","end":"20240103T120000Z",
+             "status":"completed"},
+            {"id":0,"description":"Forgotten","status":"deleted"},
+            {"id":2,"description":"Water plants","wait":"20240105T000000Z","status":"waiting"}
+        ]"#;
+        let tasks = taskwarrior(json).unwrap();
+        let lines = tasks.iter().map(|t| t.to_string()).collect::<Vec<String>>();
+        // The times are in UTC, so the dates depend on the local time zone
+        let local = |day: u8, hours: i64| {
+            let date = Date::new(2024, 1, day).unwrap();
+            local_date(date.to_unix_timestamp() + hours * 3600)
+        };
+        assert_eq!(
+            lines,
+            [
+                format!(
+                    "(A) {} Fix the roof due:{} +House_repairs @home @weekend",
+                    local(2, 10),
+                    local(10, 23)
+                ),
+                format!("x {} {} Pay rent", local(3, 12), local(1, 0)),
+                format!("Water plants t:{}", local(5, 0)),
+            ]
+        );
+
+        // One object per line, as exported by Taskwarrior 2.4
+        let lines = "{\"description\":\"a\"},\n{\"description\":\"b\"}\n";
+        assert_eq!(taskwarrior(lines).unwrap().len(), 2);
+        assert!(taskwarrior("[{\"status\":\"pending\"}]").is_err());
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let tasks = [
+            "(B) 2024-01-02 Call \"Bob\", the plumber due:2024-01-05",
+            "x 2024-01-04 2024-01-01 Pay rent",
+        ]
+        .map(|line| (None, line.parse::<Task>().unwrap()));
+
+        let csv = render(ExportFormat::Csv, &tasks, "%Y-%m-%d");
+        assert_eq!(super::csv(&csv).unwrap(), tasks.map(|(_, task)| task));
+    }
+
+    #[test]
+    fn csv_from_spreadsheets() {
+        let csv = "Title,Status,Due,Project\n\
+                   Renew passport,,2024-06-01,Travel plans\n\
+                   ,,,\n\
+                   \"Multi\nline\",done,,\n";
+        let tasks = super::csv(csv).unwrap();
+        assert_eq!(
+            tasks[0].to_string(),
+            "Renew passport due:2024-06-01 +Travel_plans"
+        );
+        assert_eq!(tasks[1].description(), "Multi line");
+        assert!(tasks[1].completed);
+        assert_eq!(tasks.len(), 2);
+
+        assert!(super::csv("Task,Due\nx,tomorrow\n").is_err());
+        assert!(super::csv("Due\n2024-01-01\n").is_err());
+    }
+
+    #[test]
+    fn plain_lines() {
+        let list = "# Groceries\n\
+                    - [ ] Milk\n\
+                    * [x] Bread\n\
+                    \n\
+                    ## Work\n\
+                    (A) Call Bob +work\n";
+        let tasks = lines(list);
+        let texts = tasks.iter().map(|t| t.to_string()).collect::<Vec<String>>();
+        assert_eq!(texts[0], "Milk");
+        assert!(tasks[1].completed && tasks[1].description() == "Bread");
+        assert_eq!(texts[2], "(A) Call Bob +work");
+        assert_eq!(texts.len(), 3);
+
+        // Without list items, a line starting with '#' is a task
+        let tasks = lines("# Groceries\nCall Bob\n");
+        assert_eq!(tasks[0].to_string(), "# Groceries");
+        assert_eq!(tasks.len(), 2);
+    }
+
+    #[test]
+    fn duplicates_are_removed() {
+        let existing = ["Call Bob", "x 2024-01-02 2024-01-01 Pay rent"]
+            .map(|line| line.parse::<Task>().unwrap());
+        let tasks = ["call  BOB", "Pay rent", "Water plants", "water plants"]
+            .map(|line| line.parse::<Task>().unwrap())
+            .to_vec();
+        let (new, duplicates) = remove_duplicates(existing.iter(), tasks);
+        assert_eq!(new, ["Water plants".parse::<Task>().unwrap()]);
+        assert_eq!(duplicates.len(), 3);
+    }
+}
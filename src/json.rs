@@ -0,0 +1,137 @@
+use crate::config::OutputFormat;
+use mama::Task;
+use mama::TaskId;
+use serde_json::{json, Map, Value};
+
+/// Version of the JSON output, incremented whenever a field is removed
+/// or changes meaning. Adding fields doesn't change the version.
+///
+/// Every document has a "version" field and one of
+/// - "tasks": the tasks listed by `ls`, all tasks after a change, or the
+///   tasks that `import --dry-run` would add
+/// - "changed": tasks that were added or modified by a command, with
+///   their new contents
+/// - "removed": tasks that were deleted, archived or modified by a
+///   command, with their old contents
+/// - "error": the message of an error that stopped the command
+/// - "config", "views" or "history": the output of the named command
+///
+/// In ndjson output, each of the tasks is a document of its own, e.g.
+/// `{"version":1,"task":{...}}`.
+pub const VERSION: u32 = 1;
+
+/// Returns the JSON object of `task`, which has the ID `id` unless it
+/// has been archived
+pub fn task(id: Option<&TaskId>, task: &Task) -> Value {
+    let date = |date: Option<gregorian::Date>| date.map(|date| date.to_string());
+
+    // Only the first value of each tag, as in `Task::tag`
+    let mut tags = Map::new();
+    for (key, value) in task.tags() {
+        tags.entry(key.as_str())
+            .or_insert_with(|| Value::from(value.as_str()));
+    }
+
+    json!({
+        "id": id.map(|id| id.0 + 1),
+        "line": task.to_string(),
+        "completed": task.completed,
+        "priority": task.priority.map(|p| p.to_string()),
+        "completion_date": date(task.completion_date),
+        "creation_date": date(task.creation_date),
+        "due_date": date(task.due_date()),
+        "threshold_date": date(task.threshold_date()),
+        "description": task.description(),
+        "projects": task.projects(),
+        "contexts": task.contexts(),
+        "tags": tags,
+    })
+}
+
+/// Prints a document with a single `field`
+pub fn print(field: &str, value: Value) {
+    let mut document = Map::new();
+    document.insert("version".to_string(), Value::from(VERSION));
+    document.insert(field.to_string(), value);
+    println!("{}", Value::Object(document));
+}
+
+/// Prints the tasks listed by a command. `tasks` are the task objects of
+/// `field`, e.g. "tasks" or "removed".
+pub fn print_tasks(format: OutputFormat, field: &str, tasks: Vec<Value>) {
+    match format {
+        OutputFormat::Ndjson => {
+            // "tasks" becomes "task" and so on
+            let field = field.strip_suffix('s').unwrap_or(field);
+            for task in tasks {
+                print(field, task);
+            }
+        }
+        _ => print(field, Value::Array(tasks)),
+    }
+}
+
+/// Prints the changes made by a command, followed by all tasks in
+/// todo.txt
+pub fn print_changes(
+    format: OutputFormat,
+    changed: Vec<Value>,
+    removed: Vec<Value>,
+    tasks: Vec<Value>,
+) {
+    match format {
+        OutputFormat::Ndjson => {
+            print_tasks(format, "changed", changed);
+            print_tasks(format, "removed", removed);
+            print_tasks(format, "tasks", tasks);
+        }
+        _ => println!(
+            "{}",
+            json!({
+                "version": VERSION,
+                "changed": changed,
+                "removed": removed,
+                "tasks": tasks,
+            })
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn task_fields() {
+        let line = "(A) 2024-01-02 Call Bob +work @phone due:2024-01-05 due:2024-02-01";
+        let value = task(Some(&TaskId(2)), &line.parse::<Task>().unwrap());
+        assert_eq!(
+            value,
+            json!({
+                "id": 3,
+                "line": line,
+                "completed": false,
+                "priority": "A",
+                "completion_date": null,
+                "creation_date": "2024-01-02",
+                "due_date": "2024-01-05",
+                "threshold_date": null,
+                "description": "Call Bob +work @phone due:2024-01-05 due:2024-02-01",
+                "projects": ["work"],
+                "contexts": ["phone"],
+                "tags": {"due": "2024-01-05"},
+            })
+        );
+    }
+
+    #[test]
+    fn archived_tasks_have_no_id() {
+        let value = task(
+            None,
+            &"x 2024-01-03 2024-01-01 Pay rent".parse::<Task>().unwrap(),
+        );
+        assert_eq!(value["id"], Value::Null);
+        assert_eq!(value["completed"], Value::Bool(true));
+        assert_eq!(value["completion_date"], "2024-01-03");
+    }
+}
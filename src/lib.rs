@@ -0,0 +1,76 @@
+//! Reading and modifying [todo.txt](http://todotxt.org/) files, as done by
+//! the `mama` command line application.
+//!
+//! A [`Task`] is a single line of todo.txt. It is parsed with
+//! [`str::parse`] and written back with [`ToString::to_string`], and keeps
+//! its projects, contexts and `key:value` tags as they were written.
+//!
+//! A [`TodoFile`] is the list of tasks in a todo.txt, each identified by
+//! a [`TaskId`]. Its operations, such as [`TodoFile::add`],
+//! [`TodoFile::complete`] and [`TodoFile::delete`], return the tasks they
+//! changed instead of printing anything, and [`TodoFile::save`] writes the
+//! changes back to the disk. Completed tasks can be moved to done.txt,
+//! a [`DoneFile`].
+//!
+//! [`Filter`] and [`SortOrder`] select and order tasks as `mama ls` does.
+//!
+//! ```
+//! use mama::{Filter, Task, TaskPriority, TodoFile};
+//!
+//! let task = "(A) 2024-01-02 Call Bob +work due:2024-01-05"
+//!     .parse::<Task>()
+//!     .unwrap();
+//! assert_eq!(task.priority, Some(TaskPriority('A')));
+//! assert_eq!(task.projects(), ["work"]);
+//!
+//! let mut todo = TodoFile::default();
+//! let id = todo.add(task);
+//! todo.add("Water the plants @home".parse().unwrap());
+//!
+//! let filter = Filter::parse(&["+work".to_string()]).unwrap();
+//! let work = todo.iter().filter(|(_, task)| filter.matches(task));
+//! assert_eq!(work.map(|(id, _)| *id).collect::<Vec<_>>(), [id]);
+//!
+//! let (completed, _) = todo.complete(id).unwrap();
+//! assert!(completed.to_string().starts_with("x "));
+//! ```
+
+#![warn(missing_docs)]
+
+/// Parsing and formatting of dates
+pub mod date;
+/// done.txt, the archive of completed tasks
+pub mod done_file;
+/// The error type of mama
+pub mod error;
+/// Exporting tasks to other formats
+pub mod export;
+/// Selecting tasks with filters such as `+work due<today`
+pub mod filter;
+/// Undo history of todo.txt
+pub mod history;
+/// Importing tasks from other applications
+pub mod import;
+/// Recurring tasks, `rec:` tags
+pub mod recurrence;
+/// Ordering of tasks, e.g. `priority,-due`
+pub mod sort;
+/// A single task
+pub mod task;
+/// Identifiers of tasks in todo.txt
+pub mod task_id;
+/// Priorities of tasks, `(A)` to `(Z)`
+pub mod task_priority;
+/// todo.txt, the list of tasks
+pub mod todo_file;
+
+pub use done_file::DoneFile;
+pub use error::Error;
+pub use filter::Filter;
+pub use history::History;
+pub use recurrence::Recurrence;
+pub use sort::SortOrder;
+pub use task::Task;
+pub use task_id::TaskId;
+pub use task_priority::TaskPriority;
+pub use todo_file::TodoFile;
@@ -1,40 +1,132 @@
 mod commands;
 use commands::*;
 
-mod error;
-mod task;
-mod task_id;
-mod task_priority;
-mod todo_file;
+mod config;
+mod json;
 
-pub use task::Task;
-pub use task_id::TaskId;
-pub use task_priority::TaskPriority;
-pub use todo_file::TodoFile;
+pub use config::Config;
+use mama::error;
+use mama::{History, TodoFile};
 
 use std::str::FromStr;
+use std::time::Duration;
 
-fn main() -> std::io::Result<()> {
-    let mut args = std::env::args();
-    let subcommand = args.nth(1).unwrap_or_else(|| "ls".to_string());
+fn main() {
+    let mut args = std::env::args().skip(1).collect::<Vec<String>>();
+
+    // Global options precede the subcommand
+    let mut file = None;
+    let mut output = None;
+    loop {
+        match args.first().map(String::as_str) {
+            Some("--json") => {
+                output = Some(config::OutputFormat::Json);
+                args.remove(0);
+            }
+            Some(option @ ("-f" | "--file" | "--format")) => {
+                if args.len() < 2 {
+                    eprintln!("⛔ Error: {}\n", error::Error::InsufficientArguments);
+                    std::process::exit(1);
+                }
+                match option {
+                    "--format" => match args[1].parse() {
+                        Ok(format) => output = Some(format),
+                        Err(x) => {
+                            eprintln!("⛔ Error: {}", x);
+                            std::process::exit(1);
+                        }
+                    },
+                    _ => file = Some(args[1].clone()),
+                }
+                args.drain(..2);
+            }
+            _ => break,
+        }
+    }
+
+    let config = match Config::load(file.as_deref(), output) {
+        Ok(x) => x,
+        Err(x) => {
+            eprintln!("⛔ Error: {}", x);
+            std::process::exit(1);
+        }
+    };
+
+    if args.is_empty() {
+        args = config.default_command.value.clone();
+    }
+    let command_line = args.join(" ");
+    let mut args = args.into_iter();
+    let subcommand = args.next().unwrap_or_default();
     let sub_args = args.collect::<Vec<String>>();
 
-    let mut todo = match TodoFile::new() {
+    let path = &config.todo_file.value;
+    let command = Commands::from_str(&subcommand).ok();
+
+    // Commands that modify the list hold a lock until the list is saved
+    let todo = match &command {
+        Some(x) if x.modifies_list(&sub_args) => {
+            TodoFile::open_locked(path, Duration::from_secs(config.lock_timeout.value))
+        }
+        _ => TodoFile::open(path).map_err(|x| {
+            error::Error::Io(format!(
+                "Unable to open {} for reading: {}",
+                path.display(),
+                x
+            ))
+        }),
+    };
+    let mut todo = match todo {
         Ok(x) => x,
         Err(x) => {
-            eprintln!("Unable to open todo.txt for reading.");
-            return Err(x);
+            eprintln!("⛔ Error: {}", x);
+            std::process::exit(1);
         }
     };
 
-    match Commands::from_str(&subcommand) {
-        Ok(x) => x.run(&mut todo, &sub_args),
-        _ => println!("{}", Commands::Help.usage()),
+    let succeeded = match &command {
+        Some(x) => x.run(&mut todo, &config, &sub_args),
+        None => {
+            println!("{}", Commands::Help.usage());
+            true
+        }
+    };
+    // With --json or --format ndjson, the changes are printed only once
+    // they have been saved
+    let changes = match &command {
+        Some(x) if succeeded && x.modifies_list(&sub_args) => match config.output.value {
+            config::OutputFormat::Human => None,
+            _ => Some(json_changes(&todo)),
+        },
+        _ => None,
     };
 
     if todo.unwritten_changes() {
-        todo.save().expect("Error while saving todo.txt");
+        let before = std::fs::read_to_string(todo.path()).unwrap_or_default();
+        let archived = todo.archived().to_vec();
+        if let Err(x) = todo.save() {
+            eprintln!("⛔ Error: {}", x);
+            std::process::exit(1);
+        }
+
+        let retention = config.backup_retention.value;
+        if let Err(x) = History::open(&todo.history_dir()).and_then(|mut history| {
+            history.record(
+                &command_line,
+                &before,
+                &todo.contents(),
+                &archived,
+                retention,
+            )
+        }) {
+            eprintln!("⚠ Unable to record the change for undo: {}", x);
+        }
     }
 
-    Ok(())
+    if let Some((changed, removed, tasks)) = changes {
+        json::print_changes(config.output.value, changed, removed, tasks);
+    }
+    if !succeeded {
+        std::process::exit(1);
+    }
 }
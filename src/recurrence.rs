@@ -0,0 +1,216 @@
+use gregorian::{Date, DateResultExt};
+
+/// The unit of a recurrence interval, the letter after the amount
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecurrenceUnit {
+    /// d
+    Days,
+    /// b, Monday to Friday
+    BusinessDays,
+    /// w
+    Weeks,
+    /// m
+    Months,
+    /// y
+    Years,
+}
+
+/// Recurrence is the interval in a `rec:` tag, e.g. `rec:1w` or `rec:+3m`.
+///
+/// By default, the next instance of a recurring task is due an interval
+/// after the task was completed. In strict mode, marked with a leading '+',
+/// the interval is counted from the previous due date instead.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Recurrence {
+    /// Whether the interval is counted from the previous due date
+    pub strict: bool,
+    /// Number of units in the interval
+    pub amount: u16,
+    /// Unit of the interval
+    pub unit: RecurrenceUnit,
+}
+
+/// Error in parsing a `rec:` interval
+#[derive(Debug, PartialEq)]
+pub enum RecurrenceError {
+    /// The amount is not a positive number
+    InvalidAmount,
+    /// The unit is not one of d, b, w, m and y
+    InvalidUnit,
+}
+
+impl Recurrence {
+    /// Returns the date one interval after `date`. Adding months or years
+    /// to a day that does not exist in the resulting month gives the last
+    /// day of that month, e.g. 2021-01-31 + 1m is 2021-02-28.
+    pub fn next_after(&self, date: Date) -> Date {
+        let amount = self.amount as i32;
+        match self.unit {
+            RecurrenceUnit::Days => date.add_days(amount),
+            RecurrenceUnit::BusinessDays => add_business_days(date, amount),
+            RecurrenceUnit::Weeks => date.add_days(7 * amount),
+            RecurrenceUnit::Months => date.add_months(amount).or_prev_valid(),
+            RecurrenceUnit::Years => date.add_years(self.amount as i16).or_prev_valid(),
+        }
+    }
+}
+
+/// Returns 0 for Monday, 1 for Tuesday, ..., 6 for Sunday
+fn weekday(date: Date) -> i32 {
+    let monday = Date::new(1970, 1, 5).unwrap();
+    monday.days_since(date).rem_euclid(7)
+}
+
+fn add_business_days(mut date: Date, amount: i32) -> Date {
+    let mut remaining = amount;
+    while remaining > 0 {
+        date = date.next();
+        if weekday(date) < 5 {
+            remaining -= 1;
+        }
+    }
+    date
+}
+
+impl std::fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let unit = match self.unit {
+            RecurrenceUnit::Days => 'd',
+            RecurrenceUnit::BusinessDays => 'b',
+            RecurrenceUnit::Weeks => 'w',
+            RecurrenceUnit::Months => 'm',
+            RecurrenceUnit::Years => 'y',
+        };
+        match self.strict {
+            true => write!(f, "+{}{}", self.amount, unit),
+            false => write!(f, "{}{}", self.amount, unit),
+        }
+    }
+}
+
+impl std::str::FromStr for Recurrence {
+    type Err = RecurrenceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (strict, s) = match s.strip_prefix('+') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+
+        let unit = match s.chars().last() {
+            Some('d') => RecurrenceUnit::Days,
+            Some('b') => RecurrenceUnit::BusinessDays,
+            Some('w') => RecurrenceUnit::Weeks,
+            Some('m') => RecurrenceUnit::Months,
+            Some('y') => RecurrenceUnit::Years,
+            _ => return Err(RecurrenceError::InvalidUnit),
+        };
+
+        // The amount may be omitted, "rec:w" is the same as "rec:1w"
+        let amount = match &s[..s.len() - 1] {
+            "" => 1,
+            n => n
+                .parse::<u16>()
+                .map_err(|_| RecurrenceError::InvalidAmount)?,
+        };
+        if amount == 0 {
+            return Err(RecurrenceError::InvalidAmount);
+        }
+
+        Ok(Recurrence {
+            strict,
+            amount,
+            unit,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i16, month: u8, day: u8) -> Date {
+        Date::new(year, month, day).unwrap()
+    }
+
+    fn rec(s: &str) -> Recurrence {
+        s.parse::<Recurrence>().unwrap()
+    }
+
+    #[test]
+    fn parse_recurrence() {
+        assert_eq!(
+            "1w".parse::<Recurrence>(),
+            Ok(Recurrence {
+                strict: false,
+                amount: 1,
+                unit: RecurrenceUnit::Weeks
+            })
+        );
+        assert_eq!(
+            "+12m".parse::<Recurrence>(),
+            Ok(Recurrence {
+                strict: true,
+                amount: 12,
+                unit: RecurrenceUnit::Months
+            })
+        );
+        assert_eq!(rec("y").amount, 1);
+        assert_eq!(rec("5b").unit, RecurrenceUnit::BusinessDays);
+    }
+
+    #[test]
+    fn invalid_recurrence() {
+        assert_eq!("".parse::<Recurrence>(), Err(RecurrenceError::InvalidUnit));
+        assert_eq!(
+            "1x".parse::<Recurrence>(),
+            Err(RecurrenceError::InvalidUnit)
+        );
+        assert_eq!(
+            "0d".parse::<Recurrence>(),
+            Err(RecurrenceError::InvalidAmount)
+        );
+        assert_eq!(
+            "-1d".parse::<Recurrence>(),
+            Err(RecurrenceError::InvalidAmount)
+        );
+    }
+
+    #[test]
+    fn formatting_round_trips() {
+        for s in ["1d", "+2b", "3w", "+1m", "10y"] {
+            assert_eq!(rec(s).to_string(), s);
+        }
+    }
+
+    #[test]
+    fn days_and_weeks() {
+        assert_eq!(rec("3d").next_after(date(2021, 12, 30)), date(2022, 1, 2));
+        assert_eq!(rec("2w").next_after(date(2021, 2, 20)), date(2021, 3, 6));
+    }
+
+    #[test]
+    fn month_end_rolls_back_to_last_day_of_month() {
+        assert_eq!(rec("1m").next_after(date(2021, 1, 31)), date(2021, 2, 28));
+        assert_eq!(rec("1m").next_after(date(2024, 1, 31)), date(2024, 2, 29));
+        assert_eq!(rec("1m").next_after(date(2021, 3, 31)), date(2021, 4, 30));
+        assert_eq!(rec("2m").next_after(date(2021, 12, 31)), date(2022, 2, 28));
+        assert_eq!(rec("1m").next_after(date(2021, 2, 28)), date(2021, 3, 28));
+    }
+
+    #[test]
+    fn leap_day_rolls_back_in_other_years() {
+        assert_eq!(rec("1y").next_after(date(2024, 2, 29)), date(2025, 2, 28));
+        assert_eq!(rec("4y").next_after(date(2024, 2, 29)), date(2028, 2, 29));
+    }
+
+    #[test]
+    fn business_days_skip_weekends() {
+        // 2021-06-11 is a Friday
+        assert_eq!(rec("1b").next_after(date(2021, 6, 11)), date(2021, 6, 14));
+        assert_eq!(rec("5b").next_after(date(2021, 6, 11)), date(2021, 6, 18));
+        // Saturday
+        assert_eq!(rec("1b").next_after(date(2021, 6, 12)), date(2021, 6, 14));
+        assert_eq!(rec("2b").next_after(date(2021, 6, 9)), date(2021, 6, 11));
+    }
+}
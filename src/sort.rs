@@ -0,0 +1,265 @@
+use crate::Task;
+use std::cmp::Ordering;
+
+/// A property of a task that tasks can be sorted by
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortField {
+    /// Priority, highest first
+    Priority,
+    /// Due date, earliest first
+    Due,
+    /// Creation date, earliest first
+    Created,
+    /// Completion date, earliest first
+    Completed,
+    /// First project, alphabetically
+    Project,
+    /// First context, alphabetically
+    Context,
+    /// Description, alphabetically ignoring case
+    Description,
+}
+
+/// A field to sort by and its direction
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SortKey {
+    /// The field to compare
+    pub field: SortField,
+    /// Whether the order is reversed, a '-' prefix
+    pub reverse: bool,
+}
+
+/// SortOrder is a list of sort keys, such as `priority,-due`. Tasks are
+/// compared by the first key, ties are broken by the next one and so on.
+/// A key prefixed with '-' sorts in reverse.
+///
+/// Tasks missing the field, e.g. tasks without a due date when sorting
+/// by due date, go last in either direction.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SortOrder(pub Vec<SortKey>);
+
+/// Error in parsing a sort order, with the unknown field
+#[derive(Debug, PartialEq)]
+pub struct UnknownSortField(pub String);
+
+impl SortOrder {
+    /// Returns true if tasks are kept in the order of the file
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Compares two tasks by the sort keys in turn
+    pub fn compare(&self, a: &Task, b: &Task) -> Ordering {
+        self.0
+            .iter()
+            .map(|key| key.compare(a, b))
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    }
+
+    /// Returns the order with the direction of every key flipped. Tasks
+    /// missing a field still go last.
+    pub fn reversed(&self) -> SortOrder {
+        SortOrder(
+            self.0
+                .iter()
+                .map(|key| SortKey {
+                    reverse: !key.reverse,
+                    ..*key
+                })
+                .collect(),
+        )
+    }
+
+    /// Sorts `tasks` in this order. Tasks that compare equal keep their
+    /// relative order.
+    pub fn sort<T>(&self, tasks: &mut [T], task: impl Fn(&T) -> &Task) {
+        if !self.is_empty() {
+            tasks.sort_by(|a, b| self.compare(task(a), task(b)));
+        }
+    }
+}
+
+impl SortKey {
+    fn compare(&self, a: &Task, b: &Task) -> Ordering {
+        let lowercase = |s: &str| s.to_lowercase();
+        match self.field {
+            SortField::Priority => {
+                self.compare_options(a.priority.map(|p| p.0), b.priority.map(|p| p.0))
+            }
+            SortField::Due => self.compare_options(a.due_date(), b.due_date()),
+            SortField::Created => self.compare_options(a.creation_date, b.creation_date),
+            SortField::Completed => self.compare_options(a.completion_date, b.completion_date),
+            SortField::Project => self.compare_options(
+                a.projects().first().map(|p| lowercase(p)),
+                b.projects().first().map(|p| lowercase(p)),
+            ),
+            SortField::Context => self.compare_options(
+                a.contexts().first().map(|c| lowercase(c)),
+                b.contexts().first().map(|c| lowercase(c)),
+            ),
+            SortField::Description => self.compare_options(
+                Some(lowercase(a.description())),
+                Some(lowercase(b.description())),
+            ),
+        }
+    }
+
+    fn compare_options<T: Ord>(&self, a: Option<T>, b: Option<T>) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) if self.reverse => b.cmp(&a),
+            (Some(a), Some(b)) => a.cmp(&b),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        }
+    }
+}
+
+impl std::str::FromStr for SortField {
+    type Err = UnknownSortField;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pri" | "priority" => Ok(SortField::Priority),
+            "due" => Ok(SortField::Due),
+            "created" => Ok(SortField::Created),
+            "completed" | "done" => Ok(SortField::Completed),
+            "project" => Ok(SortField::Project),
+            "context" => Ok(SortField::Context),
+            "desc" | "description" => Ok(SortField::Description),
+            _ => Err(UnknownSortField(s.to_string())),
+        }
+    }
+}
+
+impl std::str::FromStr for SortOrder {
+    type Err = UnknownSortField;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .filter(|key| !key.is_empty())
+            .map(|key| {
+                let (reverse, field) = match key.strip_prefix('-') {
+                    Some(field) => (true, field),
+                    None => (false, key),
+                };
+                Ok(SortKey {
+                    field: field.parse()?,
+                    reverse,
+                })
+            })
+            .collect::<Result<Vec<SortKey>, UnknownSortField>>()
+            .map(SortOrder)
+    }
+}
+
+impl std::fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let keys = self
+            .0
+            .iter()
+            .map(|key| {
+                let field = match key.field {
+                    SortField::Priority => "priority",
+                    SortField::Due => "due",
+                    SortField::Created => "created",
+                    SortField::Completed => "completed",
+                    SortField::Project => "project",
+                    SortField::Context => "context",
+                    SortField::Description => "description",
+                };
+                match key.reverse {
+                    true => format!("-{}", field),
+                    false => field.to_string(),
+                }
+            })
+            .collect::<Vec<String>>();
+        write!(f, "{}", keys.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(order: &str, lines: &[&str]) -> Vec<String> {
+        let mut tasks = lines
+            .iter()
+            .map(|line| line.parse::<Task>().unwrap())
+            .collect::<Vec<Task>>();
+        order.parse::<SortOrder>().unwrap().sort(&mut tasks, |x| x);
+        tasks
+            .iter()
+            .map(|task| task.description().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn parse_sort_order() {
+        assert_eq!(
+            "pri,-due".parse::<SortOrder>(),
+            Ok(SortOrder(vec![
+                SortKey {
+                    field: SortField::Priority,
+                    reverse: false
+                },
+                SortKey {
+                    field: SortField::Due,
+                    reverse: true
+                }
+            ]))
+        );
+        assert_eq!("".parse::<SortOrder>(), Ok(SortOrder::default()));
+        assert_eq!(
+            "due,size".parse::<SortOrder>(),
+            Err(UnknownSortField("size".to_string()))
+        );
+    }
+
+    #[test]
+    fn formatting_round_trips() {
+        let order = "priority,-due,description".parse::<SortOrder>().unwrap();
+        assert_eq!(order.to_string().parse::<SortOrder>(), Ok(order));
+    }
+
+    #[test]
+    fn missing_values_go_last() {
+        let lines = ["none", "(B) b", "(A) a"];
+        assert_eq!(sorted("priority", &lines), ["a", "b", "none"]);
+        assert_eq!(sorted("-priority", &lines), ["b", "a", "none"]);
+
+        let order = "priority".parse::<SortOrder>().unwrap().reversed();
+        assert_eq!(order, "-priority".parse().unwrap());
+    }
+
+    #[test]
+    fn later_keys_break_ties() {
+        let lines = [
+            "(A) late due:2021-07-01",
+            "(B) early due:2021-06-01",
+            "(A) early due:2021-06-01",
+        ];
+        assert_eq!(
+            sorted("pri,due", &lines),
+            [
+                "early due:2021-06-01",
+                "late due:2021-07-01",
+                "early due:2021-06-01"
+            ]
+        );
+        assert_eq!(
+            sorted("due,-pri", &lines),
+            [
+                "early due:2021-06-01",
+                "early due:2021-06-01",
+                "late due:2021-07-01"
+            ]
+        );
+    }
+
+    #[test]
+    fn equal_tasks_keep_file_order() {
+        let lines = ["b +x", "a +x", "c"];
+        assert_eq!(sorted("project", &lines), ["b +x", "a +x", "c"]);
+        assert_eq!(sorted("description", &lines), ["a +x", "b +x", "c"]);
+    }
+}
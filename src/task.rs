@@ -1,17 +1,43 @@
+use crate::Recurrence;
 use gregorian::Date;
-use iterate::iterate;
 
+/// Task is a single line of todo.txt, e.g.
+/// `(A) 2024-01-02 Call Bob +work @phone due:2024-01-05`. The projects,
+/// contexts and tags are parsed from the description, which keeps them in
+/// the order they were written.
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Task {
-    pub description: String,
+    description: String,
+    /// Whether the line starts with an `x`
     pub completed: bool,
+    /// Priority in parentheses, e.g. `(A)`
     pub priority: Option<crate::TaskPriority>,
+    /// Date the task was completed, only for completed tasks
     pub completion_date: Option<Date>,
+    /// Date the task was added to the list
     pub creation_date: Option<Date>,
+    /// `+project` tokens of the description, without the leading '+'
+    projects: Vec<String>,
+    /// `@context` tokens of the description, without the leading '@'
+    contexts: Vec<String>,
+    /// `key:value` tokens of the description
+    tags: Vec<(String, String)>,
 }
 
 impl Task {
+    /// Creates an uncompleted task with no priority or dates
+    pub fn new(description: &str) -> Self {
+        let mut task = Task::default();
+        task.set_description(description);
+        task
+    }
+
+    /// Marks the task as completed today. As is customary in todo.txt,
+    /// the priority is removed and kept in a `pri:` tag instead.
     pub fn complete(&mut self) {
+        if let Some(priority) = self.priority.take() {
+            self.set_tag("pri", &priority.to_string());
+        }
         self.completed = true;
         self.completion_date = Some(Date::today());
         // todo.txt must have a creation date whenever it has a completion date
@@ -19,10 +45,263 @@ impl Task {
             self.creation_date = Date::new(1970, 1, 1).ok();
         }
     }
+    /// Marks the task as unfinished, restoring a priority kept in
+    /// a `pri:` tag
     pub fn uncomplete(&mut self) {
         self.completed = false;
         self.completion_date = None;
+        let stashed = self
+            .tag("pri")
+            .and_then(|pri| format!("({})", pri).parse::<crate::TaskPriority>().ok());
+        if let Some(priority) = stashed {
+            self.remove_tag("pri");
+            self.priority = self.priority.or(Some(priority));
+        }
+    }
+
+    /// The text of the task after the completion mark, priority and dates,
+    /// including its projects, contexts and tags
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Replaces the description, updating projects, contexts and tags
+    pub fn set_description(&mut self, description: &str) {
+        self.description = description.trim().to_string();
+        self.parse_description();
+    }
+
+    /// Returns the projects of the task in the order they appear
+    pub fn projects(&self) -> &[String] {
+        &self.projects
+    }
+
+    /// Returns the contexts of the task in the order they appear
+    pub fn contexts(&self) -> &[String] {
+        &self.contexts
+    }
+
+    /// Returns the `key:value` tags of the task in the order they appear
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    /// Returns the value of the first tag named `key`
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns the date of the `due:` tag, if it holds a valid date
+    pub fn due_date(&self) -> Option<Date> {
+        self.tag("due")?.parse::<Date>().ok()
+    }
+
+    /// Sets or, if `date` is None, removes the `due:` tag
+    pub fn set_due_date(&mut self, date: Option<Date>) {
+        match date {
+            Some(date) => self.set_tag("due", &date.to_string()),
+            None => self.remove_tag("due"),
+        }
+    }
+
+    /// An unfinished task is overdue if its due date has passed
+    pub fn is_overdue(&self) -> bool {
+        !self.completed && self.due_date().is_some_and(|due| due < Date::today())
+    }
+
+    /// Returns true if the task is unfinished and due today
+    pub fn is_due_today(&self) -> bool {
+        !self.completed && self.due_date() == Some(Date::today())
+    }
+
+    /// Returns the date of the `t:` tag, the day the task becomes relevant
+    pub fn threshold_date(&self) -> Option<Date> {
+        self.tag("t")?.parse::<Date>().ok()
+    }
+
+    /// Sets or, if `date` is None, removes the `t:` tag
+    pub fn set_threshold_date(&mut self, date: Option<Date>) {
+        match date {
+            Some(date) => self.set_tag("t", &date.to_string()),
+            None => self.remove_tag("t"),
+        }
+    }
+
+    /// A task whose threshold date has not yet been reached is hidden by default
+    pub fn is_future(&self) -> bool {
+        self.threshold_date().is_some_and(|t| t > Date::today())
+    }
+
+    /// Returns the interval of the `rec:` tag, if the task recurs
+    pub fn recurrence(&self) -> Option<Recurrence> {
+        self.tag("rec")?.parse::<Recurrence>().ok()
+    }
+
+    /// Creates the next instance of a recurring task that was completed on
+    /// `completed_on`, or returns None if the task does not recur.
+    ///
+    /// The due and threshold dates of the new task are moved forward by
+    /// the recurrence interval. In strict mode the interval is added to the
+    /// old dates. Otherwise the new due date is counted from `completed_on`
+    /// and the threshold date keeps its distance to the due date.
+    pub fn next_occurrence(&self, completed_on: Date) -> Option<Task> {
+        let recurrence = self.recurrence()?;
+        let mut next = Task {
+            completed: false,
+            completion_date: None,
+            creation_date: Some(completed_on),
+            ..self.clone()
+        };
+
+        let due = self.due_date();
+        let threshold = self.threshold_date();
+        if recurrence.strict {
+            next.set_due_date(due.map(|d| recurrence.next_after(d)));
+            next.set_threshold_date(threshold.map(|t| recurrence.next_after(t)));
+        } else if let Some(due) = due {
+            let next_due = recurrence.next_after(completed_on);
+            next.set_due_date(Some(next_due));
+            next.set_threshold_date(threshold.map(|t| next_due.sub_days(t.days_since(due))));
+        } else {
+            next.set_threshold_date(threshold.map(|_| recurrence.next_after(completed_on)));
+        }
+        Some(next)
+    }
+
+    /// Returns true if the task has `+project`
+    pub fn has_project(&self, project: &str) -> bool {
+        self.projects.iter().any(|p| p == project)
+    }
+
+    /// Returns true if the task has `@context`
+    pub fn has_context(&self, context: &str) -> bool {
+        self.contexts.iter().any(|c| c == context)
+    }
+
+    /// Appends `+project` to the description unless it is already there
+    pub fn add_project(&mut self, project: &str) {
+        if !self.has_project(project) {
+            self.push_word(&format!("+{}", project));
+        }
+    }
+
+    /// Removes every `+project` from the description
+    pub fn remove_project(&mut self, project: &str) {
+        self.remove_words(|word| word.strip_prefix('+') == Some(project));
+    }
+
+    /// Appends `@context` to the description unless it is already there
+    pub fn add_context(&mut self, context: &str) {
+        if !self.has_context(context) {
+            self.push_word(&format!("@{}", context));
+        }
+    }
+
+    /// Removes every `@context` from the description
+    pub fn remove_context(&mut self, context: &str) {
+        self.remove_words(|word| word.strip_prefix('@') == Some(context));
+    }
+
+    /// Sets the value of tag `key`. An existing tag is updated in place,
+    /// otherwise the tag is appended to the description.
+    pub fn set_tag(&mut self, key: &str, value: &str) {
+        let tag = format!("{}:{}", key, value);
+        let mut seen = false;
+        let description = rebuild(&self.description, |word| match parse_tag(word) {
+            Some((k, _)) if k == key && seen => None,
+            Some((k, _)) if k == key => {
+                seen = true;
+                Some(tag.clone())
+            }
+            _ => Some(word.to_string()),
+        });
+
+        if seen {
+            self.description = description;
+            self.parse_description();
+        } else {
+            self.push_word(&tag);
+        }
+    }
+
+    /// Removes every `key:value` tag with `key` from the description
+    pub fn remove_tag(&mut self, key: &str) {
+        self.remove_words(|word| matches!(parse_tag(word), Some((k, _)) if k == key));
+    }
+
+    fn push_word(&mut self, word: &str) {
+        if !self.description.is_empty() {
+            self.description.push(' ');
+        }
+        self.description.push_str(word);
+        self.parse_description();
+    }
+
+    fn remove_words<F: Fn(&str) -> bool>(&mut self, remove: F) {
+        self.description = rebuild(&self.description, |word| match remove(word) {
+            true => None,
+            false => Some(word.to_string()),
+        });
+        self.parse_description();
+    }
+
+    fn parse_description(&mut self) {
+        self.projects.clear();
+        self.contexts.clear();
+        self.tags.clear();
+
+        for word in self.description.split_whitespace() {
+            if let Some(project) = word.strip_prefix('+').filter(|p| !p.is_empty()) {
+                if !self.has_project(project) {
+                    self.projects.push(project.to_string());
+                }
+            } else if let Some(context) = word.strip_prefix('@').filter(|c| !c.is_empty()) {
+                if !self.has_context(context) {
+                    self.contexts.push(context.to_string());
+                }
+            } else if let Some((key, value)) = parse_tag(word) {
+                self.tags.push((key.to_string(), value.to_string()));
+            }
+        }
+    }
+}
+
+/// Splits a `key:value` word into its key and value. Keys consist of
+/// alphanumeric characters, '-' and '_'. Words like URLs whose value
+/// starts with "//" are not considered tags.
+fn parse_tag(word: &str) -> Option<(&str, &str)> {
+    let (key, value) = word.split_once(':')?;
+    let valid_key = key
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+
+    match valid_key && !key.is_empty() && !value.is_empty() && !value.starts_with("//") {
+        true => Some((key, value)),
+        false => None,
+    }
+}
+
+/// Rebuilds `s` word by word, replacing each word with the result of `f`
+/// or dropping it if `f` returns None. The whitespace preceding kept words
+/// is preserved.
+fn rebuild<F: FnMut(&str) -> Option<String>>(s: &str, mut f: F) -> String {
+    let mut result = String::new();
+    let mut previous_end = 0;
+
+    for word in s.split_whitespace() {
+        let start = word.as_ptr() as usize - s.as_ptr() as usize;
+        if let Some(replacement) = f(word) {
+            if !result.is_empty() {
+                result.push_str(&s[previous_end..start]);
+            }
+            result.push_str(&replacement);
+        }
+        previous_end = start + word.len();
     }
+    result
 }
 
 impl std::fmt::Display for Task {
@@ -44,8 +323,10 @@ impl std::fmt::Display for Task {
     }
 }
 
+/// Error in parsing a task
 #[derive(Debug, PartialEq)]
 pub enum TaskParsingError {
+    /// The line is empty or only has whitespace
     EmptyLine,
 }
 
@@ -55,6 +336,9 @@ impl std::str::FromStr for Task {
     // Using regular expressions might make this a lot cleaner,
     // but it would also increase the binary size by a couple of MB
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Byte offset of a token within `s`
+        let offset = |token: &str| token.as_ptr() as usize - s.as_ptr() as usize;
+
         let mut tokens = s.split_whitespace();
         let mut cur = tokens.next();
 
@@ -102,18 +386,15 @@ impl std::str::FromStr for Task {
             _ => None,
         };
 
-        // remainder is the description:
-        let description = iterate![cur.unwrap(), ..tokens]
-            .map(std::string::ToString::to_string)
-            .collect::<Vec<String>>()
-            .join(" ");
+        // remainder is the description, with its original spacing intact:
+        let description = cur.map_or("", |token| s[offset(token)..].trim_end());
 
         Ok(Task {
-            description,
             completed,
             priority,
             completion_date,
             creation_date,
+            ..Task::new(description)
         })
     }
 }
@@ -136,7 +417,18 @@ mod tests {
 
     #[test]
     fn description_only() {
-        assert_eq!("one two".parse::<Task>().unwrap().description, "one two");
+        assert_eq!("one two".parse::<Task>().unwrap().description(), "one two");
+    }
+
+    #[test]
+    fn description_spacing_is_preserved() {
+        assert_eq!(
+            "(A) one  two\tthree "
+                .parse::<Task>()
+                .unwrap()
+                .description(),
+            "one  two\tthree"
+        );
     }
 
     #[test]
@@ -156,7 +448,7 @@ mod tests {
             Some(TaskPriority('A'))
         );
         assert_eq!(
-            "x (A) description".parse::<Task>().unwrap().description,
+            "x (A) description".parse::<Task>().unwrap().description(),
             "description"
         );
     }
@@ -170,21 +462,21 @@ mod tests {
     fn parse_creation_date() {
         assert_eq!(
             "2019-11-01 abc".parse::<Task>().unwrap().creation_date,
-            Date::new(2019, 11, 01).ok()
+            Date::new(2019, 11, 1).ok()
         );
     }
 
     #[test]
     fn parse_completion_date() {
         let task = "2019-11-02 2019-11-01 abc".parse::<Task>().unwrap();
-        assert_eq!(task.creation_date, Date::new(2019, 11, 01).ok());
-        assert_eq!(task.completion_date, Date::new(2019, 11, 02).ok());
+        assert_eq!(task.creation_date, Date::new(2019, 11, 1).ok());
+        assert_eq!(task.completion_date, Date::new(2019, 11, 2).ok());
     }
 
     #[test]
     fn initial_x_not_parsed_as_description() {
         assert_eq!(
-            "x description".parse::<Task>().unwrap().description,
+            "x description".parse::<Task>().unwrap().description(),
             "description"
         );
     }
@@ -219,10 +511,164 @@ mod tests {
         assert_eq!(task.completion_date, None);
     }
 
+    #[test]
+    fn priority_is_stashed_on_completion() {
+        let mut task = "(B) 2021-06-09 Call mom".parse::<Task>().unwrap();
+        task.complete();
+        assert_eq!(task.priority, None);
+        assert_eq!(task.tag("pri"), Some("B"));
+
+        task.uncomplete();
+        assert_eq!(task.priority, Some(crate::TaskPriority('B')));
+        assert_eq!(task.to_string(), "(B) 2021-06-09 Call mom");
+    }
+
     #[test]
     fn completed_task_must_also_have_creation_date() {
         let mut task = "description".parse::<Task>().unwrap();
         task.complete();
         assert_eq!(task.creation_date, Date::new(1970, 1, 1).ok());
     }
+
+    #[test]
+    fn projects_and_contexts_are_parsed_in_order() {
+        let task = "Call @phone +b mom +a @home +b +".parse::<Task>().unwrap();
+        assert_eq!(task.projects(), ["b", "a"]);
+        assert_eq!(task.contexts(), ["phone", "home"]);
+    }
+
+    #[test]
+    fn tags_are_parsed() {
+        let task = "Pay rent due:2021-07-01 see https://example.com a:b:c"
+            .parse::<Task>()
+            .unwrap();
+        assert_eq!(task.tag("due"), Some("2021-07-01"));
+        assert_eq!(task.tag("https"), None);
+        assert_eq!(task.tag("a"), Some("b:c"));
+        assert_eq!(task.tags().len(), 2);
+    }
+
+    #[test]
+    fn adding_project_and_context() {
+        let mut task = "(B) Paint  fence".parse::<Task>().unwrap();
+        task.add_project("garden");
+        task.add_project("garden");
+        task.add_context("home");
+        assert_eq!(task.projects(), ["garden"]);
+        assert_eq!(task.contexts(), ["home"]);
+        assert_eq!(task.to_string(), "(B) Paint  fence +garden @home");
+    }
+
+    #[test]
+    fn removing_project_and_context() {
+        let mut task = Task::new("+garden Paint @home fence  +garden");
+        task.remove_project("garden");
+        task.remove_context("home");
+        assert!(task.projects().is_empty());
+        assert!(task.contexts().is_empty());
+        assert_eq!(task.description(), "Paint fence");
+    }
+
+    #[test]
+    fn setting_and_removing_tags() {
+        let mut task = Task::new("Pay rent due:2021-07-01  now");
+        task.set_tag("due", "2021-08-01");
+        assert_eq!(task.description(), "Pay rent due:2021-08-01  now");
+        task.set_tag("t", "2021-07-25");
+        assert_eq!(task.tag("t"), Some("2021-07-25"));
+        task.remove_tag("due");
+        assert_eq!(task.description(), "Pay rent  now t:2021-07-25");
+    }
+
+    #[test]
+    fn formatting_is_unchanged_by_parsing_tokens() {
+        let line = "(A) 2021-06-01 Fix +roof @home due:2021-06-05 today";
+        assert_eq!(line.parse::<Task>().unwrap().to_string(), line);
+    }
+
+    #[test]
+    fn due_date_is_parsed() {
+        let task = "Pay rent due:2021-07-01".parse::<Task>().unwrap();
+        assert_eq!(task.due_date(), Date::new(2021, 7, 1).ok());
+        assert_eq!(Task::new("Pay rent due:someday").due_date(), None);
+    }
+
+    #[test]
+    fn setting_due_date() {
+        let mut task = Task::new("Pay rent");
+        task.set_due_date(Date::new(2021, 7, 1).ok());
+        assert_eq!(task.description(), "Pay rent due:2021-07-01");
+        task.set_due_date(None);
+        assert_eq!(task.description(), "Pay rent");
+    }
+
+    #[test]
+    fn overdue_tasks() {
+        let mut task = Task::new("Pay rent");
+        task.set_due_date(Some(Date::today().prev()));
+        assert!(task.is_overdue());
+        task.complete();
+        assert!(!task.is_overdue());
+
+        let mut task = Task::new("Pay rent");
+        task.set_due_date(Some(Date::today()));
+        assert!(!task.is_overdue());
+        assert!(task.is_due_today());
+    }
+
+    #[test]
+    fn threshold_date() {
+        let mut task = "Renew passport t:2021-07-01".parse::<Task>().unwrap();
+        assert_eq!(task.threshold_date(), Date::new(2021, 7, 1).ok());
+        assert!(!task.is_future());
+
+        task.set_threshold_date(Some(Date::today().next()));
+        assert!(task.is_future());
+        task.set_threshold_date(None);
+        assert_eq!(task.description(), "Renew passport");
+    }
+
+    #[test]
+    fn task_without_recurrence_does_not_recur() {
+        let task = Task::new("Water plants due:2021-06-01");
+        assert_eq!(task.next_occurrence(Date::today()), None);
+        assert_eq!(Task::new("Water plants rec:often").recurrence(), None);
+    }
+
+    #[test]
+    fn recurrence_counts_from_completion() {
+        let task = "(A) 2021-05-01 Water plants due:2021-06-01 t:2021-05-30 rec:1w"
+            .parse::<Task>()
+            .unwrap();
+        let completed_on = Date::new(2021, 6, 10).unwrap();
+        let next = task.next_occurrence(completed_on).unwrap();
+        assert_eq!(
+            next.to_string(),
+            "(A) 2021-06-10 Water plants due:2021-06-17 t:2021-06-15 rec:1w"
+        );
+    }
+
+    #[test]
+    fn strict_recurrence_counts_from_due_date() {
+        let task = "x 2021-02-02 2021-01-01 Pay rent due:2021-01-31 t:2021-01-25 rec:+1m"
+            .parse::<Task>()
+            .unwrap();
+        let completed_on = Date::new(2021, 2, 2).unwrap();
+        let next = task.next_occurrence(completed_on).unwrap();
+        assert!(!next.completed);
+        assert_eq!(next.completion_date, None);
+        assert_eq!(next.creation_date, Some(completed_on));
+        assert_eq!(next.due_date(), Date::new(2021, 2, 28).ok());
+        assert_eq!(next.threshold_date(), Date::new(2021, 2, 25).ok());
+    }
+
+    #[test]
+    fn recurrence_without_due_date_moves_threshold() {
+        let task = Task::new("Call grandma t:2021-06-01 rec:2w");
+        let next = task
+            .next_occurrence(Date::new(2021, 6, 3).unwrap())
+            .unwrap();
+        assert_eq!(next.due_date(), None);
+        assert_eq!(next.threshold_date(), Date::new(2021, 6, 17).ok());
+    }
 }
@@ -30,13 +30,13 @@ impl std::str::FromStr for TaskId {
 impl std::cmp::PartialEq<usize> for TaskId {
     #[inline]
     fn eq(&self, other: &usize) -> bool {
-        self.0.eq(&other)
+        self.0.eq(other)
     }
 }
 
 impl std::cmp::PartialOrd<usize> for TaskId {
     fn partial_cmp(&self, other: &usize) -> Option<Ordering> {
-        self.0.partial_cmp(&other)
+        self.0.partial_cmp(other)
     }
 }
 
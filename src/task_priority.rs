@@ -1,3 +1,5 @@
+/// TaskPriority is the priority of a task, an uppercase letter from A,
+/// the highest, to Z. It is written in parentheses in todo.txt, e.g. `(A)`.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct TaskPriority(pub char);
 
@@ -7,10 +9,38 @@ impl std::fmt::Display for TaskPriority {
     }
 }
 
+impl TaskPriority {
+    /// Returns the priority one letter closer to A, or None for A
+    pub fn higher(self) -> Option<Self> {
+        match self.0 {
+            'A' => None,
+            c => Some(TaskPriority((c as u8 - 1) as char)),
+        }
+    }
+
+    /// Parses a priority written as a letter, e.g. "a" or "B", rather
+    /// than in parentheses as in todo.txt
+    pub fn from_letter(s: &str) -> Result<Self, PriorityError> {
+        format!("({})", s.to_uppercase()).parse()
+    }
+
+    /// Returns the priority one letter closer to Z, or None for Z
+    pub fn lower(self) -> Option<Self> {
+        match self.0 {
+            'Z' => None,
+            c => Some(TaskPriority((c as u8 + 1) as char)),
+        }
+    }
+}
+
+/// Error in parsing a priority
 #[derive(Debug, PartialEq)]
 pub enum PriorityError {
+    /// The priority is not three characters long, e.g. `(A)`
     InvalidLength,
+    /// The priority is not in parentheses
     NoParentheses,
+    /// The letter is not from A to Z
     InvalidCharacter,
 }
 
@@ -41,6 +71,14 @@ impl std::cmp::PartialEq<char> for TaskPriority {
 mod tests {
     use super::*;
 
+    #[test]
+    fn stepping_priorities() {
+        assert_eq!(TaskPriority('B').higher(), Some(TaskPriority('A')));
+        assert_eq!(TaskPriority('A').higher(), None);
+        assert_eq!(TaskPriority('Y').lower(), Some(TaskPriority('Z')));
+        assert_eq!(TaskPriority('Z').lower(), None);
+    }
+
     #[test]
     fn empty_string() {
         assert!("".parse::<TaskPriority>().is_err());
@@ -1,137 +1,1020 @@
+use crate::error::Error;
+use crate::DoneFile;
 use crate::Task;
 use crate::TaskId;
-use std::collections::BTreeMap;
+use gregorian::Date;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::io::{self, Write};
 use std::iter::Iterator;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-#[derive(Default)]
+/// A single line of todo.txt
+#[derive(Clone, Debug, PartialEq)]
+enum Line {
+    /// A line that does not contain a task, e.g. a blank separator line.
+    /// It is written back exactly as it was read.
+    Passthrough(String),
+    /// A line containing a task. `original` is the text the task was parsed
+    /// from, or None if the task was added after the file was read.
+    Task {
+        id: TaskId,
+        task: Task,
+        original: Option<String>,
+    },
+}
+
+impl Line {
+    /// Returns the text to write to the disk for this line. Tasks that have
+    /// not been modified since they were read are written back verbatim.
+    fn text(&self) -> String {
+        match self {
+            Line::Passthrough(text) => text.clone(),
+            Line::Task {
+                task,
+                original: Some(original),
+                ..
+            } if original.parse::<Task>().as_ref() == Ok(task) => original.clone(),
+            Line::Task { task, .. } => task.to_string(),
+        }
+    }
+
+    /// Returns true for tasks that have changed since they were read
+    fn is_modified(&self) -> bool {
+        match self {
+            Line::Task {
+                task,
+                original: Some(original),
+                ..
+            } => original.parse::<Task>().as_ref() != Ok(task),
+            _ => false,
+        }
+    }
+}
+
+/// Identifies a version of a file on the disk
+#[derive(Clone, Debug, PartialEq)]
+struct Fingerprint {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+}
+
+impl Fingerprint {
+    /// Reads the file at `path`, returning its contents and fingerprint,
+    /// or None if the file does not exist
+    fn read(path: &Path) -> io::Result<Option<(String, Self)>> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(x) if x.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(x) => return Err(x),
+        };
+        let metadata = std::fs::metadata(path)?;
+
+        let mut hasher = DefaultHasher::new();
+        contents.hash(&mut hasher);
+        let fingerprint = Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            hash: hasher.finish(),
+        };
+        Ok(Some((contents, fingerprint)))
+    }
+}
+
+/// TodoFile is todo.txt, the list of tasks. Lines that are not tasks, such
+/// as blank lines, are kept as they are. Changes are made in memory and
+/// written to the disk by `save`.
 pub struct TodoFile {
-    tasks: BTreeMap<TaskId, Task>,
+    path: PathBuf,
+    lines: Vec<Line>,
+    /// Line terminator used in the file, either "\n" or "\r\n"
+    newline: &'static str,
+    /// Whether the last line of the file is terminated by a newline
+    final_newline: bool,
     changed: bool,
+    /// The file on the disk when it was last read or written,
+    /// or None if it did not exist
+    fingerprint: Option<Fingerprint>,
+    /// Text of each task when the file was last read or written
+    saved_tasks: Vec<String>,
+    /// Whether the list was replaced as a whole, e.g. by undo
+    replaced: bool,
+    /// Text of the tasks archived since the file was last written, to be
+    /// appended to done.txt when saving
+    archived: Vec<String>,
+    /// Directory of the lock and the undo history, or None for mama's
+    /// directory in the user's cache directory
+    cache_dir: Option<PathBuf>,
+    /// Lock file held until the TodoFile is dropped, see `open_locked`
+    _lock: Option<std::fs::File>,
 }
 
-impl TodoFile {
-    pub fn new() -> std::io::Result<Self> {
-        match Self::from(&Self::path()) {
-            Err(x) if x.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
-            x => x,
+impl Default for TodoFile {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::new(),
+            lines: Vec::new(),
+            newline: "\n",
+            final_newline: true,
+            changed: false,
+            fingerprint: None,
+            saved_tasks: Vec::new(),
+            replaced: false,
+            archived: Vec::new(),
+            cache_dir: None,
+            _lock: None,
         }
     }
+}
 
-    pub fn from(path: &std::path::Path) -> std::io::Result<Self> {
-        let masks = std::fs::read_to_string(path)?
-            .lines()
-            .filter_map(|s| s.parse::<Task>().ok())
-            .enumerate()
-            .map(|(a, t)| (TaskId(a), t))
-            .collect();
+impl TodoFile {
+    /// Reads the todo.txt at `path`. If the file does not exist,
+    /// the list is empty and the file is created when saving.
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let (todo, fingerprint) = match Fingerprint::read(path)? {
+            Some((contents, fingerprint)) => (Self::parse(&contents), Some(fingerprint)),
+            None => (Self::default(), None),
+        };
 
         Ok(Self {
-            tasks: masks,
-            changed: false,
+            path: path.to_path_buf(),
+            fingerprint,
+            ..todo
         })
     }
 
+    /// Reads the todo.txt at `path` like `open`, but first takes an exclusive
+    /// lock on it, so that other mama processes don't modify the file until
+    /// the returned TodoFile is dropped. Waits at most `timeout` for the
+    /// lock to be released.
+    ///
+    /// The lock is advisory and kept on a separate file in the cache
+    /// directory, as saving replaces todo.txt with a new file.
+    pub fn open_locked(path: &Path, timeout: Duration) -> Result<Self, Error> {
+        Self::open_locked_in(path, &default_cache_dir(), timeout)
+    }
+
+    /// Like `open_locked`, but keeps the lock and the undo history in
+    /// `cache_dir` instead of the user's cache directory
+    pub fn open_locked_in(path: &Path, cache_dir: &Path, timeout: Duration) -> Result<Self, Error> {
+        let lock_path = cache_path(path, cache_dir, "lock");
+        let io_error =
+            |e: io::Error| Error::Io(format!("Unable to lock {}: {}", path.display(), e));
+        if let Some(dir) = lock_path.parent() {
+            std::fs::create_dir_all(dir).map_err(io_error)?;
+        }
+        let lock = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&lock_path)
+            .map_err(io_error)?;
+
+        let start = Instant::now();
+        loop {
+            match lock.try_lock() {
+                Ok(()) => break,
+                Err(std::fs::TryLockError::WouldBlock) if start.elapsed() < timeout => {
+                    std::thread::sleep(Duration::from_millis(20))
+                }
+                Err(std::fs::TryLockError::WouldBlock) => {
+                    return Err(Error::Locked(path.display().to_string()))
+                }
+                Err(std::fs::TryLockError::Error(e)) => return Err(io_error(e)),
+            }
+        }
+
+        let todo = Self::open(path).map_err(|e| {
+            Error::Io(format!(
+                "Unable to open {} for reading: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Ok(Self {
+            cache_dir: Some(cache_dir.to_path_buf()),
+            _lock: Some(lock),
+            ..todo
+        })
+    }
+
+    /// Replaces the tasks with those in the file at `path`
+    pub fn restore(&mut self, path: &Path) -> std::io::Result<()> {
+        self.replace_contents(&std::fs::read_to_string(path)?);
+        Ok(())
+    }
+
+    /// Replaces the whole list with `contents`, the text of a todo.txt file
+    pub fn replace_contents(&mut self, contents: &str) {
+        let replacement = Self::parse(contents);
+        self.lines = replacement.lines;
+        self.newline = replacement.newline;
+        self.final_newline = replacement.final_newline;
+        self.changed = true;
+        self.replaced = true;
+    }
+
+    /// Parses the contents of a todo.txt file. Lines that do not contain
+    /// a task are kept as they are, so that `contents` can reproduce
+    /// the original text exactly.
+    fn parse(contents: &str) -> Self {
+        let newline = match contents.find('\n') {
+            Some(i) if contents[..i].ends_with('\r') => "\r\n",
+            _ => "\n",
+        };
+        let final_newline = contents.is_empty() || contents.ends_with('\n');
+
+        let mut texts = contents.split(newline).collect::<Vec<&str>>();
+        if contents.ends_with(newline) || contents.is_empty() {
+            // split() yields an empty string after the final terminator
+            texts.pop();
+        }
+
+        let mut next_id = 0;
+        let lines = texts
+            .into_iter()
+            .map(|text| match text.parse::<Task>() {
+                Ok(task) => {
+                    next_id += 1;
+                    Line::Task {
+                        id: TaskId(next_id - 1),
+                        task,
+                        original: Some(text.to_string()),
+                    }
+                }
+                Err(_) => Line::Passthrough(text.to_string()),
+            })
+            .collect::<Vec<Line>>();
+        let saved_tasks = lines
+            .iter()
+            .filter_map(|line| match line {
+                Line::Task { original, .. } => original.clone(),
+                Line::Passthrough(_) => None,
+            })
+            .collect();
+
+        Self {
+            lines,
+            newline,
+            final_newline,
+            saved_tasks,
+            ..Self::default()
+        }
+    }
+
+    /// Re-applies the changes made since the file was read onto `contents`,
+    /// the current text of the file after someone else has changed it.
+    /// Modified and deleted tasks are looked up by their original text and
+    /// added tasks are appended to the end. Returns false, leaving the list
+    /// unchanged, if a task we changed was also changed in `contents`.
+    fn rebase(&mut self, contents: &str) -> bool {
+        if self.replaced {
+            return false;
+        }
+
+        // Tasks that were read from the file and have not been deleted
+        let mut kept = self
+            .lines
+            .iter()
+            .filter_map(|line| match line {
+                Line::Task {
+                    original: Some(original),
+                    ..
+                } => Some(original.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<&str>>();
+        let mut deleted = Vec::new();
+        for original in &self.saved_tasks {
+            match kept.iter().position(|x| x == original) {
+                Some(i) => {
+                    kept.swap_remove(i);
+                }
+                None => deleted.push(original.as_str()),
+            }
+        }
+
+        let theirs = Self::parse(contents);
+        let mut lines = theirs.lines.into_iter().map(Some).collect::<Vec<_>>();
+        let mut claimed = vec![false; lines.len()];
+        let mut claim = |original: &str| {
+            let i = lines.iter().zip(&claimed).position(|(line, claimed)| {
+                !claimed
+                    && matches!(line, Some(Line::Task { original: Some(x), .. }) if x == original)
+            })?;
+            claimed[i] = true;
+            Some(i)
+        };
+
+        let mut changes = Vec::new();
+        for original in deleted {
+            changes.push((claim(original), None));
+        }
+        for line in self.lines.iter().filter(|line| line.is_modified()) {
+            if let Line::Task {
+                original: Some(original),
+                ..
+            } = line
+            {
+                changes.push((claim(original), Some(line.clone())));
+            }
+        }
+        if changes.iter().any(|(i, _)| i.is_none()) {
+            return false;
+        }
+        for (i, line) in changes {
+            lines[i.unwrap()] = line;
+        }
+
+        let added = self
+            .lines
+            .iter()
+            .filter(|line| matches!(line, Line::Task { original: None, .. }));
+        lines.extend(added.cloned().map(Some));
+        self.lines = lines.into_iter().flatten().collect();
+        self.newline = theirs.newline;
+        self.final_newline = theirs.final_newline;
+        self.refresh_ids();
+        true
+    }
+
+    /// Returns the text of the file as it would be written to the disk
+    pub fn contents(&self) -> String {
+        let mut contents = self
+            .lines
+            .iter()
+            .map(Line::text)
+            .collect::<Vec<String>>()
+            .join(self.newline);
+        if self.final_newline && !self.lines.is_empty() {
+            contents.push_str(self.newline);
+        }
+        contents
+    }
+
+    /// Returns the IDs of the tasks that were added or modified since the
+    /// file was last read or written, and the tasks that were removed or
+    /// replaced by the modified ones
+    pub fn changes(&self) -> (Vec<TaskId>, Vec<Task>) {
+        let mut removed = self.saved_tasks.iter().collect::<Vec<&String>>();
+        let mut changed = Vec::new();
+        for line in &self.lines {
+            if let Line::Task { id, .. } = line {
+                let text = line.text();
+                match removed.iter().position(|saved| **saved == text) {
+                    Some(i) => {
+                        removed.remove(i);
+                    }
+                    None => changed.push(*id),
+                }
+            }
+        }
+
+        let removed = removed
+            .into_iter()
+            .filter_map(|text| text.parse::<Task>().ok())
+            .collect();
+        (changed, removed)
+    }
+
+    /// Returns true if the list has changed since it was read or saved
     pub fn unwritten_changes(&self) -> bool {
         self.changed
     }
 
     /// Checks whether `id` is a valid identifier for the file
     pub fn has_id(&self, id: &TaskId) -> bool {
-        self.tasks.contains_key(id)
+        self.iter().any(|(x, _)| x == id)
     }
 
-    /// After deleting a task, the keys in `tasks` are not necessarily
-    /// just a simple enumeration of the values anymore. This fixes that.
+    /// After deleting a task, the task IDs are not necessarily
+    /// just a simple enumeration of the tasks anymore. This fixes that.
     pub fn refresh_ids(&mut self) {
-        self.tasks = self
-            .tasks
-            .values()
-            .enumerate()
-            .map(|(id, task)| (TaskId(id), task.clone()))
-            .collect();
+        let mut next_id = 0;
+        for line in self.lines.iter_mut() {
+            if let Line::Task { id, .. } = line {
+                *id = TaskId(next_id);
+                next_id += 1;
+            }
+        }
     }
 
-    /// Writes the tasks to the disk, backing up any pre-existing file
-    pub fn save(&mut self) -> std::io::Result<()> {
-        if Self::path().exists() {
-            // Backup existing todo.txt
-            std::fs::copy(Self::path(), Self::undo_path())?;
+    /// Writes the tasks to the disk.
+    /// Lines that have not been modified are written back unchanged.
+    ///
+    /// The tasks are first written to a temporary file in the same directory,
+    /// which then replaces todo.txt, so an interrupted save leaves either the
+    /// old or the new list intact. The permissions of todo.txt are preserved,
+    /// and if it is a symbolic link, the file it points to is replaced.
+    ///
+    /// If the file has been changed by someone else since it was read,
+    /// our changes are re-applied onto the current contents of the file.
+    /// When that is not possible, nothing is written and
+    /// `Error::ConcurrentModification` is returned.
+    ///
+    /// Archived tasks are appended to done.txt once todo.txt has been
+    /// written. If that fails, todo.txt is put back as it was, so that the
+    /// tasks are in exactly one of the files.
+    pub fn save(&mut self) -> Result<(), Error> {
+        let path = self.path.clone();
+        let io_error =
+            |e: io::Error| Error::Io(format!("Unable to save {}: {}", path.display(), e));
+
+        let current = Fingerprint::read(&path).map_err(io_error)?;
+        let (previous, fingerprint) = current.unzip();
+        let changed_elsewhere = fingerprint != self.fingerprint;
+        if changed_elsewhere && !self.rebase(previous.as_deref().unwrap_or_default()) {
+            return Err(Error::ConcurrentModification(path.display().to_string()));
         }
+        self.write(&self.contents()).map_err(io_error)?;
 
-        let mut file = std::fs::File::create(Self::path())?;
-        self.tasks().try_for_each(|s| writeln!(file, "{}", s))?;
+        let done_path = self.done_path();
+        let archived = DoneFile::open(&done_path).and_then(|mut done| done.append(&self.archived));
+        if let Err(e) = archived {
+            // Put back todo.txt as it was, which may be no file at all
+            let _ = match &previous {
+                Some(previous) => self.write(previous),
+                None => std::fs::remove_file(&path),
+            };
+            return Err(Error::Io(format!(
+                "Unable to write to {}: {}",
+                done_path.display(),
+                e
+            )));
+        }
+        self.archived.clear();
+
+        // What was written is now the baseline for detecting changes
+        let saved = Self::parse(&self.contents());
+        self.lines = saved.lines;
+        self.saved_tasks = saved.saved_tasks;
+        self.fingerprint = Fingerprint::read(&path)
+            .map_err(io_error)?
+            .map(|(_, fingerprint)| fingerprint);
         self.changed = false;
+        self.replaced = false;
+
+        Ok(())
+    }
+
+    /// Writes `contents` to the disk through a temporary file
+    fn write(&self, contents: &str) -> io::Result<()> {
+        let path = std::fs::canonicalize(&self.path).unwrap_or_else(|_| self.path.clone());
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let temp_path = dir.join(format!(".{}.{}.tmp", name, std::process::id()));
+
+        let result = self
+            .write_temporary(contents, &temp_path, &path)
+            .and_then(|_| std::fs::rename(&temp_path, &path));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp_path);
+        }
+        result?;
+
+        // Make the rename itself durable. Directories can't be opened
+        // for syncing on all platforms, so this is best effort.
+        if let Ok(dir) = std::fs::File::open(dir) {
+            let _ = dir.sync_all();
+        }
 
         Ok(())
     }
 
+    /// Writes `contents` to a new file at `temp_path`, with the permissions
+    /// of the file at `path` if it exists, and flushes it to the disk
+    fn write_temporary(&self, contents: &str, temp_path: &Path, path: &Path) -> io::Result<()> {
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(temp_path)?;
+        file.write_all(contents.as_bytes())?;
+        if let Ok(metadata) = std::fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.sync_all()
+    }
+
     /// Returns an iterator of the (TaskId, Task) pairs
     pub fn iter(&self) -> impl Iterator<Item = (&TaskId, &Task)> {
-        self.tasks.iter()
+        self.lines.iter().filter_map(|line| match line {
+            Line::Task { id, task, .. } => Some((id, task)),
+            Line::Passthrough(_) => None,
+        })
     }
 
     /// Returns an iterator over the tasks.
-    pub fn tasks(&self) -> std::collections::btree_map::Values<TaskId, Task> {
-        self.tasks.values()
+    pub fn tasks(&self) -> impl Iterator<Item = &Task> {
+        self.iter().map(|(_, task)| task)
     }
 
-    pub fn add(&mut self, task: Task) {
-        let id = TaskId(self.tasks.len());
-        self.tasks.insert(id, task);
+    /// Appends `task` to the end of the list and returns its ID
+    pub fn add(&mut self, task: Task) -> TaskId {
+        let id = self
+            .iter()
+            .map(|(id, _)| TaskId(id.0 + 1))
+            .max()
+            .unwrap_or(TaskId(0));
+        self.lines.push(Line::Task {
+            id,
+            task,
+            original: None,
+        });
         self.changed = true;
+        id
+    }
+
+    /// Returns the ID of the task tagged with `id:<stable_id>`
+    pub fn find_stable_id(&self, stable_id: &str) -> Option<TaskId> {
+        self.iter()
+            .find(|(_, task)| task.tag("id") == Some(stable_id))
+            .map(|(id, _)| *id)
+    }
+
+    /// Returns a short, random identifier for an `id:` tag that no task
+    /// in the list has yet
+    pub fn unused_stable_id(&self) -> String {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |x| x.as_nanos());
+        (0u32..)
+            .map(|attempt| {
+                let mut hasher = DefaultHasher::new();
+                (seed, std::process::id(), attempt).hash(&mut hasher);
+                let mut n = hasher.finish();
+
+                // Four base-36 digits, e.g. "k3x9"
+                (0..4)
+                    .map(|_| {
+                        let digit = std::char::from_digit((n % 36) as u32, 36).unwrap();
+                        n /= 36;
+                        digit
+                    })
+                    .collect::<String>()
+            })
+            .find(|id| self.find_stable_id(id).is_none())
+            .unwrap()
+    }
+
+    /// Returns the line of the task at `index` as it would be written to
+    /// the disk, which is the original text while the task is unmodified
+    pub fn line(&self, index: TaskId) -> Option<String> {
+        self.lines.iter().find_map(|line| match line {
+            Line::Task { id, .. } if *id == index => Some(line.text()),
+            _ => None,
+        })
     }
 
     /// Returns true if `index` is a valid, existing task ID
     pub fn has_task(&self, index: TaskId) -> bool {
-        index < self.tasks.len()
+        self.has_id(&index)
     }
 
-    /// Sets the state of task at `index` to completed.
-    /// On successs, returns the finished task. If index is out of bounds,
+    /// Applies `change` to the task at `index`.
+    /// On successs, returns the modified task. If index is out of bounds,
     /// returns None.
-    pub fn complete(&mut self, index: TaskId) -> Option<&Task> {
-        match self.tasks.get_mut(&index) {
-            Some(task) => {
-                task.complete();
-                self.changed = true;
-                Some(&*task)
-            }
-            None => None,
+    pub fn modify<F: FnOnce(&mut Task)>(&mut self, index: TaskId, change: F) -> Option<&Task> {
+        let task = self.lines.iter_mut().find_map(|line| match line {
+            Line::Task { id, task, .. } if *id == index => Some(task),
+            _ => None,
+        })?;
+        change(task);
+        self.changed = true;
+        Some(&*task)
+    }
+
+    /// Sets the state of task at `index` to completed. If the task recurs,
+    /// its next instance is added to the end of the list, with a stable
+    /// identifier of its own if the task has one.
+    /// On successs, returns the finished task and the ID of the added
+    /// instance, if any. If index is out of bounds, returns None.
+    pub fn complete(&mut self, index: TaskId) -> Option<(Task, Option<TaskId>)> {
+        let (_, task) = self.iter().find(|(id, _)| **id == index)?;
+        let mut next = match task.completed {
+            true => None,
+            false => task.next_occurrence(Date::today()),
+        };
+        if let Some(next) = next.as_mut().filter(|next| next.tag("id").is_some()) {
+            next.set_tag("id", &self.unused_stable_id());
         }
+
+        let task = self.modify(index, Task::complete)?.clone();
+        let next_id = next.map(|next| self.add(next));
+        Some((task, next_id))
     }
 
     /// Sets the state of task at `index` to uncompleted.
     /// On successs, returns the modified task. If index is out of bounds,
     /// returns an error.
     pub fn uncomplete(&mut self, index: TaskId) -> Option<&Task> {
-        match self.tasks.get_mut(&index) {
-            Some(task) => {
-                task.uncomplete();
-                self.changed = true;
-                Some(&*task)
-            }
-            None => None,
-        }
+        self.modify(index, Task::uncomplete)
     }
 
     /// Deletes the task at `index`.
     /// On successs, returns the deleted task. If index is out of bounds,
     /// returns None.
     pub fn delete(&mut self, index: TaskId) -> Option<Task> {
+        let position = self
+            .lines
+            .iter()
+            .position(|line| matches!(line, Line::Task { id, .. } if *id == index))?;
         self.changed = true;
-        self.tasks.remove(&index)
+        match self.lines.remove(position) {
+            Line::Task { task, .. } => Some(task),
+            Line::Passthrough(_) => None,
+        }
+    }
+
+    /// Removes all completed tasks from the list and returns them.
+    /// The IDs of the remaining tasks are renumbered. The tasks are
+    /// appended to done.txt, as they were written in todo.txt, when the
+    /// list is saved.
+    pub fn archive(&mut self) -> Vec<Task> {
+        let mut archived = Vec::new();
+        let mut texts = Vec::new();
+        self.lines.retain(|line| match line {
+            Line::Task { task, .. } if task.completed => {
+                archived.push(task.clone());
+                texts.push(line.text());
+                false
+            }
+            _ => true,
+        });
+        self.archived.extend(texts);
+
+        if !archived.is_empty() {
+            self.changed = true;
+            self.refresh_ids();
+        }
+        archived
+    }
+
+    /// Returns the text of the tasks that have been archived but not yet
+    /// appended to done.txt
+    pub fn archived(&self) -> &[String] {
+        &self.archived
+    }
+
+    /// Location of todo.txt
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Location of done.txt, which is kept in the same directory as todo.txt
+    pub fn done_path(&self) -> PathBuf {
+        self.path.with_file_name("done.txt")
+    }
+
+    /// Location of the undo history. Each todo.txt has its own history
+    /// in the cache directory, named after the full path of the file.
+    pub fn history_dir(&self) -> PathBuf {
+        let cache_dir = self.cache_dir.clone().unwrap_or_else(default_cache_dir);
+        cache_path(&self.path, &cache_dir, "history")
+    }
+}
+
+/// mama's directory in the user's cache directory, e.g. ~/.cache/mama
+fn default_cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .expect("Unable to determine cache directory.")
+        .join("mama")
+}
+
+/// Returns a path in `cache_dir` that is unique to the todo.txt at `path`,
+/// e.g. ~/.cache/mama/%home%user%todo.txt.history
+fn cache_path(path: &Path, cache_dir: &Path, extension: &str) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let name = path
+        .to_string_lossy()
+        .replace(std::path::MAIN_SEPARATOR, "%");
+
+    cache_dir.join(format!("{}.{}", name, extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the path of todo.txt in an empty temporary directory
+    fn temporary_todo(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mama-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("todo.txt")
+    }
+
+    #[test]
+    fn unmodified_file_is_written_back_verbatim() {
+        let contents = "(A)  2021-06-09 two  spaces\n\n   \n+project\tand tab\n";
+        assert_eq!(TodoFile::parse(contents).contents(), contents);
+    }
+
+    #[test]
+    fn lines_keep_their_original_text() {
+        let mut todo = TodoFile::parse("(A)  two  spaces\nb\n");
+        assert_eq!(todo.line(TaskId(0)).unwrap(), "(A)  two  spaces");
+        todo.modify(TaskId(0), |task| task.priority = None);
+        assert_eq!(todo.line(TaskId(0)).unwrap(), "two  spaces");
+        assert_eq!(todo.line(TaskId(2)), None);
+    }
+
+    #[test]
+    fn missing_final_newline_is_preserved() {
+        assert_eq!(TodoFile::parse("a\nb").contents(), "a\nb");
+    }
+
+    #[test]
+    fn crlf_line_endings_are_preserved() {
+        let mut todo = TodoFile::parse("a\r\n\r\nb\r\n");
+        todo.add("c".parse::<Task>().unwrap());
+        assert_eq!(todo.contents(), "a\r\n\r\nb\r\nc\r\n");
+    }
+
+    #[test]
+    fn blank_lines_are_not_tasks() {
+        let todo = TodoFile::parse("a\n\nb\n");
+        let ids = todo.iter().map(|(id, _)| *id).collect::<Vec<TaskId>>();
+        assert_eq!(ids, vec![TaskId(0), TaskId(1)]);
+    }
+
+    #[test]
+    fn only_modified_tasks_are_reserialized() {
+        let mut todo = TodoFile::parse("first  task\n\nsecond  task\n");
+        todo.uncomplete(TaskId(0));
+        todo.complete(TaskId(1));
+        let completed = todo.contents();
+        assert!(completed.starts_with("first  task\n\nx "));
+        assert!(completed.ends_with(" second  task\n"));
+    }
+
+    #[test]
+    fn deleting_keeps_other_lines() {
+        let mut todo = TodoFile::parse("a\n\nb\nc\n");
+        assert_eq!(todo.delete(TaskId(1)).unwrap().description(), "b");
+        todo.refresh_ids();
+        assert_eq!(todo.contents(), "a\n\nc\n");
+        assert!(todo.has_id(&TaskId(1)));
+        assert!(!todo.has_id(&TaskId(2)));
+    }
+
+    #[test]
+    fn completing_recurring_task_adds_next_instance() {
+        let mut todo = TodoFile::parse("Water plants rec:1w\n");
+        let (task, next_id) = todo.complete(TaskId(0)).unwrap();
+        assert!(task.completed);
+        assert_eq!(next_id, Some(TaskId(1)));
+
+        let (_, next) = todo.iter().nth(1).unwrap();
+        assert!(!next.completed);
+        assert_eq!(next.creation_date, Some(Date::today()));
+
+        // Completing the task again must not add another instance
+        assert_eq!(todo.complete(TaskId(0)).unwrap().1, None);
+        assert_eq!(todo.tasks().count(), 2);
+    }
+
+    #[test]
+    fn next_instance_gets_its_own_stable_id() {
+        let mut todo = TodoFile::parse("Water plants rec:1w id:k3x9\n");
+        todo.complete(TaskId(0));
+        let (_, next) = todo.iter().nth(1).unwrap();
+        assert_ne!(next.tag("id"), Some("k3x9"));
+        assert_eq!(todo.find_stable_id("k3x9"), Some(TaskId(0)));
+    }
+
+    #[test]
+    fn changes_since_reading() {
+        let mut todo = TodoFile::parse("a\nb\nc\n");
+        todo.modify(TaskId(1), |task| task.set_description("B"));
+        todo.delete(TaskId(0));
+        todo.add(Task::new("d"));
+
+        let (changed, removed) = todo.changes();
+        assert_eq!(changed, [TaskId(1), TaskId(3)]);
+        let removed = removed.iter().map(Task::to_string).collect::<Vec<_>>();
+        assert_eq!(removed, ["a", "b"]);
+    }
+
+    #[test]
+    fn archiving_removes_completed_tasks() {
+        let mut todo = TodoFile::parse("x a\nb\n\nx c\nd\n");
+        let archived = todo.archive();
+        assert_eq!(archived.len(), 2);
+        assert!(archived.iter().all(|task| task.completed));
+        assert_eq!(todo.contents(), "b\n\nd\n");
+        assert!(todo.has_id(&TaskId(1)));
+        assert!(todo.unwritten_changes());
+    }
+
+    #[test]
+    fn archived_tasks_are_appended_to_done_txt_when_saving() {
+        let path = temporary_todo("archive");
+        std::fs::write(path.with_file_name("done.txt"), "x old\n").unwrap();
+        std::fs::write(&path, "x  2024-01-02  two  spaces\nb\n").unwrap();
+
+        let mut todo = TodoFile::open(&path).unwrap();
+        let archived = todo.archive();
+        assert_eq!(archived[0].description(), "two  spaces");
+        assert_eq!(todo.archived(), ["x  2024-01-02  two  spaces"]);
+        assert_eq!(
+            std::fs::read_to_string(todo.done_path()).unwrap(),
+            "x old\n"
+        );
+
+        todo.save().unwrap();
+        assert!(todo.archived().is_empty());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "b\n");
+        assert_eq!(
+            std::fs::read_to_string(todo.done_path()).unwrap(),
+            "x old\nx  2024-01-02  two  spaces\n"
+        );
+    }
+
+    #[test]
+    fn failed_archive_restores_todo_txt() {
+        let path = temporary_todo("archive-failure");
+        std::fs::create_dir(path.with_file_name("done.txt")).unwrap();
+
+        let mut todo = TodoFile::open(&path).unwrap();
+        todo.add("x a".parse().unwrap());
+        todo.archive();
+        assert!(todo.save().is_err());
+        assert!(!path.exists());
+
+        std::fs::write(&path, "x a\nb\n").unwrap();
+        let mut todo = TodoFile::open(&path).unwrap();
+        todo.archive();
+        assert!(todo.save().is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "x a\nb\n");
+    }
+
+    #[test]
+    fn refused_save_does_not_archive() {
+        let path = temporary_todo("archive-conflict");
+        std::fs::write(&path, "x a\nb\n").unwrap();
+
+        let mut todo = TodoFile::open(&path).unwrap();
+        todo.archive();
+        std::fs::write(&path, "x a edited elsewhere\nb\n").unwrap();
+
+        assert!(todo.save().is_err());
+        assert!(!todo.done_path().exists());
+    }
+
+    #[test]
+    fn saving_replaces_the_file() {
+        let path = temporary_todo("save");
+        std::fs::write(&path, "old task\n").unwrap();
+
+        let mut todo = TodoFile::open(&path).unwrap();
+        todo.add(Task::new("new task"));
+        todo.save().unwrap();
+
+        assert!(!todo.unwritten_changes());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "old task\nnew task\n"
+        );
+        let files = std::fs::read_dir(path.parent().unwrap()).unwrap().count();
+        assert_eq!(files, 1, "temporary file was left behind");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn saving_preserves_permissions_and_symlinks() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temporary_todo("symlink");
+        let target = path.with_file_name("synced.txt");
+        std::fs::write(&target, "task\n").unwrap();
+        std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o600)).unwrap();
+        std::os::unix::fs::symlink(&target, &path).unwrap();
+
+        let mut todo = TodoFile::open(&path).unwrap();
+        todo.add(Task::new("another"));
+        todo.save().unwrap();
+
+        assert!(std::fs::symlink_metadata(&path)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "task\nanother\n");
+        let mode = std::fs::metadata(&target).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn changes_are_rebased_onto_external_edits() {
+        let mut todo = TodoFile::parse("a\nb\nc\nd\n");
+        todo.modify(TaskId(1), |task| task.set_description("b2"));
+        todo.delete(TaskId(2));
+        todo.add(Task::new("e"));
+
+        assert!(todo.rebase("a\n\nb\nc\nd\nfrom phone\n"));
+        assert_eq!(todo.contents(), "a\n\nb2\nd\nfrom phone\ne\n");
+        let ids = todo.iter().map(|(id, _)| id.0).collect::<Vec<usize>>();
+        assert_eq!(ids, [0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn conflicting_changes_are_not_rebased() {
+        let mut todo = TodoFile::parse("a\nb\n");
+        todo.modify(TaskId(1), |task| task.set_description("b2"));
+        assert!(!todo.rebase("a\nb edited elsewhere\n"));
+
+        let mut todo = TodoFile::parse("a\nb\n");
+        todo.delete(TaskId(0));
+        assert!(!todo.rebase("a edited elsewhere\nb\n"));
+        assert_eq!(todo.contents(), "b\n");
+    }
+
+    #[test]
+    fn saving_refuses_to_overwrite_conflicting_edit() {
+        let path = temporary_todo("conflict");
+        std::fs::write(&path, "a\nb\n").unwrap();
+
+        let mut todo = TodoFile::open(&path).unwrap();
+        todo.modify(TaskId(0), |task| task.set_description("a2"));
+        std::fs::write(&path, "a edited elsewhere\nb\n").unwrap();
+
+        let error = todo.save().unwrap_err();
+        assert!(matches!(error, Error::ConcurrentModification(_)));
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "a edited elsewhere\nb\n"
+        );
+    }
+
+    #[test]
+    fn saving_merges_external_additions() {
+        let path = temporary_todo("merge");
+        std::fs::write(&path, "a\n").unwrap();
+
+        let mut todo = TodoFile::open(&path).unwrap();
+        todo.add(Task::new("ours"));
+        std::fs::write(&path, "a\ntheirs\n").unwrap();
+        todo.save().unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\ntheirs\nours\n");
+    }
+
+    #[test]
+    fn concurrent_adds_are_not_lost() {
+        let path = temporary_todo("concurrent");
+        let threads = (0..8)
+            .map(|n| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let cache_dir = path.parent().unwrap();
+                    let mut todo =
+                        TodoFile::open_locked_in(&path, cache_dir, Duration::from_secs(30))
+                            .unwrap();
+                    todo.add(Task::new(&format!("task {}", n)));
+                    todo.save().unwrap();
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let todo = TodoFile::open(&path).unwrap();
+        assert_eq!(todo.tasks().count(), 8);
     }
 
-    pub fn path() -> std::path::PathBuf {
-        dirs::home_dir()
-            .expect("Unable to determine home directory.")
-            .join("todo.txt")
+    #[test]
+    fn lock_times_out() {
+        let path = temporary_todo("timeout");
+        let cache_dir = path.parent().unwrap();
+        let held = TodoFile::open_locked_in(&path, cache_dir, Duration::ZERO).unwrap();
+        assert!(held.history_dir().starts_with(cache_dir));
+
+        let result = TodoFile::open_locked_in(&path, cache_dir, Duration::from_millis(50));
+        assert!(matches!(result, Err(Error::Locked(_))));
     }
 
-    pub fn undo_path() -> std::path::PathBuf {
-        dirs::cache_dir()
-            .expect("Unable to determine cache directory.")
-            .join("todo.txt.backup")
+    #[test]
+    fn stable_ids_are_unique() {
+        let mut todo = TodoFile::parse("a id:k3x9\nb\n");
+        assert_eq!(todo.find_stable_id("k3x9"), Some(TaskId(0)));
+        assert_eq!(todo.find_stable_id("b"), None);
+
+        for _ in 0..100 {
+            let mut task = Task::new("task");
+            task.set_tag("id", &todo.unused_stable_id());
+            todo.add(task);
+        }
+        let mut ids = todo
+            .tasks()
+            .filter_map(|task| task.tag("id"))
+            .collect::<Vec<&str>>();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), 101);
     }
 }